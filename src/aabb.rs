@@ -23,6 +23,15 @@ pub struct Aabb {
 
 impl Aabb {
     pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> bool {
+        self.clip(r, t_min, t_max).is_some()
+    }
+
+    /// Returns the part of the range `t_min..t_max` where the ray is
+    /// inside the box
+    pub fn clip(&self, r: Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
         for a in 0..3 {
             let t0 = f32::min(
                 (self.min[a] - r.origin[a]) / r.dir[a],
@@ -34,15 +43,15 @@ impl Aabb {
                 (self.max[a] - r.origin[a]) / r.dir[a],
             );
 
-            let t_min = f32::max(t0, t_min);
-            let t_max = f32::min(t1, t_max);
+            t_min = f32::max(t0, t_min);
+            t_max = f32::min(t1, t_max);
 
             if t_max <= t_min {
-                return false;
+                return None;
             }
         }
 
-        true
+        Some((t_min, t_max))
     }
 }
 
@@ -69,4 +78,21 @@ impl Aabb {
             max: big,
         }
     }
+
+    /// The box covering the region inside both boxes. Boxes that do not
+    /// overlap produce an empty box at the edge of the first one.
+    pub fn overlap(box0: Self, box1: Self) -> Self {
+        let mut small = Vector::zeros();
+        let mut big = Vector::zeros();
+
+        for a in 0..3 {
+            small[a] = f32::max(box0.min[a], box1.min[a]);
+            big[a] = f32::max(f32::min(box0.max[a], box1.max[a]), small[a]);
+        }
+
+        Self {
+            min: small,
+            max: big,
+        }
+    }
 }
@@ -0,0 +1,121 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::BackgroundSample;
+use crate::{distribution::Distribution2D, image::Image, Vector};
+
+use rand::prelude::*;
+use std::f32::consts::PI;
+
+/// Light arriving from infinitely far away in every direction, given by
+/// an equirectangular (latitude-longitude) image. The top of the image is
+/// straight up along the y axis.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: Image,
+    /// Rotation around the y axis, in radians
+    rotation: f32,
+    intensity: f32,
+    distribution: Distribution2D,
+}
+
+impl EnvironmentMap {
+    /// Creates an environment from `image`, turned by `rotation` degrees
+    /// around the y axis and scaled in brightness by `intensity`
+    pub fn new(image: Image, rotation: f32, intensity: f32) -> Self {
+        let (width, height) = (image.width, image.height);
+
+        // Rows near the poles cover less of the sphere, so they are
+        // weighted down by the sine of their angle from the pole
+        let mut weights = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            for x in 0..width {
+                weights.push(image.luminance(x, y) * sin_theta);
+            }
+        }
+
+        Self {
+            distribution: Distribution2D::new(&weights, width),
+            image,
+            rotation: rotation.to_radians(),
+            intensity,
+        }
+    }
+
+    /// The light arriving from the direction `dir`
+    pub fn radiance(&self, dir: Vector) -> Vector {
+        let (u, v) = self.uv_of(dir);
+        let x =
+            ((u * self.image.width as f32) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f32) as usize)
+            .min(self.image.height - 1);
+
+        self.image.pixel(x, y) * self.intensity
+    }
+
+    /// Picks a direction in proportion to the brightness of the
+    /// environment
+    pub fn sample(&self) -> BackgroundSample {
+        let mut rng = thread_rng();
+        let ((u, v), pdf_uv) =
+            self.distribution.sample(rng.gen::<f32>(), rng.gen::<f32>());
+
+        let dir = self.direction_at(u, v);
+        let sin_theta = (PI * v).sin();
+        let pdf = if sin_theta > 0.0 {
+            pdf_uv / (2.0 * PI * PI * sin_theta)
+        } else {
+            0.0
+        };
+
+        BackgroundSample {
+            dir,
+            radiance: self.radiance(dir),
+            pdf,
+        }
+    }
+
+    /// The solid angle pdf of `sample` choosing the direction `dir`
+    pub fn pdf(&self, dir: Vector) -> f32 {
+        let (u, v) = self.uv_of(dir);
+        let sin_theta = (PI * v).sin();
+
+        if sin_theta > 0.0 {
+            self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+        } else {
+            0.0
+        }
+    }
+
+    fn uv_of(&self, dir: Vector) -> (f32, f32) {
+        let dir = dir / dir.length();
+        let theta = dir.y.clamp(-1.0, 1.0).acos();
+        let phi = (dir.z.atan2(dir.x) - self.rotation).rem_euclid(2.0 * PI);
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn direction_at(&self, u: f32, v: f32) -> Vector {
+        let phi = 2.0 * PI * u + self.rotation;
+        let theta = PI * v;
+
+        Vector::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+}
@@ -0,0 +1,64 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::Vector;
+
+mod environment;
+mod sky;
+
+pub use environment::EnvironmentMap;
+pub use sky::Sky;
+
+/// Light from far away, seen by rays that don't hit anything in the scene
+#[derive(Debug)]
+pub enum Background {
+    Environment(EnvironmentMap),
+    Sky(Sky),
+}
+
+/// A direction towards the background chosen for next event estimation
+#[derive(Debug, Copy, Clone)]
+pub struct BackgroundSample {
+    pub dir: Vector,
+    pub radiance: Vector,
+    pub pdf: f32,
+}
+
+impl Background {
+    /// The light arriving from the direction `dir`
+    pub fn radiance(&self, dir: Vector) -> Vector {
+        match self {
+            Self::Environment(env) => env.radiance(dir),
+            Self::Sky(sky) => sky.radiance(dir),
+        }
+    }
+
+    /// Picks a direction to sample the background in directly, if there
+    /// is one worth sampling
+    pub fn sample(&self) -> Option<BackgroundSample> {
+        match self {
+            Self::Environment(env) => Some(env.sample()),
+            Self::Sky(sky) => sky.sample(),
+        }
+    }
+
+    /// The solid angle pdf of `sample` choosing the direction `dir`
+    pub fn pdf(&self, dir: Vector) -> f32 {
+        match self {
+            Self::Environment(env) => env.pdf(dir),
+            Self::Sky(sky) => sky.pdf(dir),
+        }
+    }
+}
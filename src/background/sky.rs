@@ -0,0 +1,247 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::BackgroundSample;
+use crate::{math::Onb, Vector};
+
+use rand::prelude::*;
+use std::f32::consts::PI;
+
+/// Angular radius of the sun disk, in radians
+const SUN_RADIUS: f32 = 0.00465;
+
+/// Converts sky luminance from kcd/m^2 into the units used by the
+/// renderer, so that a clear sky comes out at around 1
+const SKY_SCALE: f32 = 0.125;
+
+/// Luminance of the sun before it passes through the atmosphere, in
+/// kcd/m^2
+const SUN_LUMINANCE: f32 = 1.6e6;
+
+/// Wavelengths used for the red, green and blue channels, in micrometers
+const WAVELENGTHS: [f32; 3] = [0.68, 0.55, 0.44];
+
+/// A clear sky after Preetham et al. with the sun in it. The sky is
+/// evaluated analytically from the position of the sun and the turbidity
+/// of the air, which ranges from around 2 on a very clear day to 10 on a
+/// hazy one. The sun can be sampled like a directional light.
+///
+/// [More info](https://courses.cs.duke.edu/cps124/fall01/resources/p91-preetham.pdf)
+#[derive(Debug)]
+pub struct Sky {
+    sun_dir: Vector,
+    sun_radiance: Vector,
+    /// Perez coefficients for luminance and the two chromaticities
+    perez: [[f32; 5]; 3],
+    /// Zenith luminance and chromaticities
+    zenith: [f32; 3],
+    /// The Perez function at the zenith, which the sky is scaled by so
+    /// that it matches the zenith values
+    zenith_perez: [f32; 3],
+    intensity: f32,
+}
+
+impl Sky {
+    /// Creates a sky with the sun `elevation` degrees above the horizon,
+    /// turned `azimuth` degrees from the +z axis towards +x. The whole
+    /// sky is scaled in brightness by `intensity`.
+    pub fn new(
+        elevation: f32,
+        azimuth: f32,
+        turbidity: f32,
+        intensity: f32,
+    ) -> Self {
+        let (el, az) = (elevation.to_radians(), azimuth.to_radians());
+        let sun_dir =
+            Vector::new(el.cos() * az.sin(), el.sin(), el.cos() * az.cos());
+
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        // The zenith formulas are fitted for the sun at or above the
+        // horizon
+        let theta_s = (PI / 2.0 - el).clamp(0.0, PI / 2.0);
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance =
+            ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+
+        let (t2, th, th2, th3) =
+            (t * t, theta_s, theta_s * theta_s, theta_s.powi(3));
+        let x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        let mut zenith_perez = [0.0; 3];
+        for (z, c) in zenith_perez.iter_mut().zip(perez.iter()) {
+            *z = perez_fn(c, 0.0, theta_s);
+        }
+
+        // The sun has set
+        let sun_radiance = if el >= 0.0 {
+            sun_radiance(theta_s, t)
+        } else {
+            Vector::zeros()
+        };
+
+        Self {
+            sun_dir,
+            sun_radiance,
+            perez,
+            zenith: [luminance, x, y],
+            zenith_perez,
+            intensity,
+        }
+    }
+
+    pub fn radiance(&self, dir: Vector) -> Vector {
+        let dir = dir / dir.length();
+
+        // Nothing comes from below the horizon
+        if dir.y <= 0.0 {
+            return Vector::zeros();
+        }
+
+        let cos_gamma = dir.dot(self.sun_dir).clamp(-1.0, 1.0);
+        let theta = dir.y.acos();
+        let gamma = cos_gamma.acos();
+
+        let mut xyy = [0.0; 3];
+        for (i, v) in xyy.iter_mut().enumerate() {
+            *v = self.zenith[i] * perez_fn(&self.perez[i], theta, gamma)
+                / self.zenith_perez[i];
+        }
+
+        let mut radiance = xyy_to_rgb(xyy[1], xyy[2], xyy[0] * SKY_SCALE);
+        for c in 0..3 {
+            radiance[c] = radiance[c].max(0.0);
+        }
+
+        if gamma < SUN_RADIUS {
+            radiance += self.sun_radiance;
+        }
+
+        radiance * self.intensity
+    }
+
+    /// Picks a direction within the sun disk
+    pub fn sample(&self) -> Option<BackgroundSample> {
+        if self.sun_radiance.x <= 0.0 {
+            return None;
+        }
+
+        let mut rng = thread_rng();
+        let cos_max = SUN_RADIUS.cos();
+        let cos_theta = 1.0 - rng.gen::<f32>() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f32>();
+
+        let dir = Onb::build_from_w(self.sun_dir).local(Vector::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        Some(BackgroundSample {
+            dir,
+            radiance: self.radiance(dir),
+            pdf: 1.0 / (2.0 * PI * (1.0 - cos_max)),
+        })
+    }
+
+    /// The solid angle pdf of `sample` choosing the direction `dir`
+    pub fn pdf(&self, dir: Vector) -> f32 {
+        let cos_max = SUN_RADIUS.cos();
+
+        if self.sun_radiance.x > 0.0
+            && dir.dot(self.sun_dir) / dir.length() >= cos_max
+        {
+            1.0 / (2.0 * PI * (1.0 - cos_max))
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The Perez sky luminance distribution for a view `theta` radians from
+/// the zenith and `gamma` radians from the sun
+fn perez_fn(c: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    // Views right at the horizon would blow up
+    let cos_theta = theta.cos().max(0.01);
+    let cos_gamma = gamma.cos();
+
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+}
+
+/// The sun's light after Rayleigh and aerosol scattering take their share
+/// on the way through the atmosphere, for the sun `theta_s` radians from
+/// the zenith
+fn sun_radiance(theta_s: f32, turbidity: f32) -> Vector {
+    // Relative optical air mass
+    let m = 1.0
+        / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let alpha = 1.3;
+
+    let mut radiance = Vector::zeros();
+    for (c, &lambda) in WAVELENGTHS.iter().enumerate() {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+        let aerosol = (-beta * lambda.powf(-alpha) * m).exp();
+        radiance[c] = SUN_LUMINANCE * SKY_SCALE * rayleigh * aerosol;
+    }
+
+    radiance
+}
+
+/// Converts a CIE xyY color to linear sRGB
+fn xyy_to_rgb(x: f32, y: f32, luminance: f32) -> Vector {
+    if y <= 0.0 {
+        return Vector::zeros();
+    }
+
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+
+    Vector::new(
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+}
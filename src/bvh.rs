@@ -36,7 +36,7 @@ pub struct Bvh {
 }
 
 impl Bvh {
-    pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
         if self.bounding_box.hit(r, t_min, t_max) {
             return match &self.node_type {
                 BvhNodeType::Internal((left, right)) => {
@@ -55,18 +55,28 @@ impl Bvh {
                         }
                     }
                 }
-                BvhNodeType::Leaf(l) => {
-                    l.primitive.intersect(r, t_min, t_max).map(|i| Hit {
-                        intersection: i,
-                        scattered: l.material.scatter(r, i),
-                        emitted: l.material.emitted(r, i),
-                    })
-                }
+                BvhNodeType::Leaf(l) => l.hit(r, t_min, t_max),
             };
         }
 
         None
     }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bounding_box
+    }
+
+    /// All of the objects in the tree
+    pub fn objects(&self) -> Vec<&Object> {
+        match &self.node_type {
+            BvhNodeType::Internal((left, right)) => {
+                let mut objects = left.objects();
+                objects.extend(right.objects());
+                objects
+            }
+            BvhNodeType::Leaf(l) => vec![l],
+        }
+    }
 }
 
 impl Bvh {
@@ -77,7 +87,7 @@ impl Bvh {
             0 => panic!("wrong bvh length"),
             1 => {
                 let obj = objects.remove(0);
-                let bounding_box = obj.primitive.bounding_box();
+                let bounding_box = obj.bounding_box();
                 Self {
                     node_type: BvhNodeType::Leaf(obj),
                     bounding_box,
@@ -87,8 +97,8 @@ impl Bvh {
                 objects.select_nth_unstable_by(
                     l / 2,
                     |a: &Object, b: &Object| {
-                        let box_left = a.primitive.bounding_box();
-                        let box_right = b.primitive.bounding_box();
+                        let box_left = a.bounding_box();
+                        let box_right = b.bounding_box();
 
                         if box_left.min[idx as usize]
                             - box_right.min[idx as usize]
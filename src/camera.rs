@@ -14,9 +14,106 @@
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
 use crate::math::random_in_unit_disk;
-use crate::{ray::Ray, Vector};
+use crate::{
+    distribution::Distribution2D, image::Image, keyframes::Keyframes, ray::Ray,
+    Vector,
+};
 
-use std::f32;
+use rand::prelude::*;
+use std::{f32, sync::Arc};
+
+/// How directions in front of the camera are laid out on the image
+#[derive(Debug, Copy, Clone)]
+pub enum Projection {
+    /// A regular camera, which sees `vfov` degrees from the bottom of the
+    /// image to the top
+    Perspective,
+    /// Parallel rays with no perspective, for technical illustrations.
+    /// `height` is how much of the scene fits from the bottom of the
+    /// image to the top, in scene units.
+    Orthographic { height: f32 },
+    /// A fisheye lens which sees `vfov` degrees across a circle as tall
+    /// as the image, which can be more than 180. The image is black
+    /// outside of the circle.
+    Fisheye(FisheyeMapping),
+    /// A full 360 by 180 degree panorama, for VR viewers. The image
+    /// should be twice as wide as it is tall.
+    Equirectangular,
+}
+
+/// How the angle from the center of a fisheye image grows with the
+/// distance from the center
+#[derive(Debug, Copy, Clone)]
+pub enum FisheyeMapping {
+    /// Equal steps in angle take up equal distances on the image
+    Equidistant,
+    /// Equal solid angles take up equal areas on the image
+    Equisolid,
+}
+
+/// The shape of the opening in the lens, which out of focus highlights
+/// take on
+#[derive(Debug, Clone)]
+pub enum ApertureShape {
+    Circle,
+    /// A regular polygon made by `blades` straight blades, turned by
+    /// `rotation` degrees
+    Polygon {
+        blades: u32,
+        rotation: f32,
+    },
+    /// Made with [`ApertureShape::from_image`]
+    ///
+    /// [`ApertureShape::from_image`]: enum.ApertureShape.html#method.from_image
+    Mask(Arc<Distribution2D>),
+}
+
+impl ApertureShape {
+    /// An aperture shaped like a grayscale image stretched over the
+    /// square around the lens. Brighter pixels let more light through.
+    pub fn from_image(image: &Image) -> Self {
+        let func: Vec<f32> = (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| image.luminance(x, y)))
+            .collect();
+
+        Self::Mask(Arc::new(Distribution2D::new(&func, image.width)))
+    }
+
+    /// A random point on the aperture, which fits in a square from -1 to 1
+    fn sample(&self) -> (f32, f32) {
+        let mut rng = rand::thread_rng();
+
+        match self {
+            Self::Circle => {
+                let p = random_in_unit_disk();
+                (p.x, p.y)
+            }
+            Self::Polygon { blades, rotation } => {
+                // Pick one of the triangles between the center and an
+                // edge, then a point in it
+                let blades = (*blades).max(3);
+                let step = 2.0 * f32::consts::PI / blades as f32;
+                let a0 = rotation.to_radians()
+                    + rng.gen_range(0, blades) as f32 * step;
+                let a1 = a0 + step;
+
+                let (mut s, mut t) = (rng.gen::<f32>(), rng.gen::<f32>());
+                if s + t > 1.0 {
+                    s = 1.0 - s;
+                    t = 1.0 - t;
+                }
+
+                (a0.cos() * s + a1.cos() * t, a0.sin() * s + a1.sin() * t)
+            }
+            Self::Mask(distribution) => {
+                let ((u, v), _) = distribution.sample(rng.gen(), rng.gen());
+
+                // Images are stored from the top down
+                (2.0 * u - 1.0, 1.0 - 2.0 * v)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Camera {
@@ -25,11 +122,21 @@ pub struct Camera {
     vertical: Vector,
     origin: Vector,
     lens_radius: f32,
+    focus_dist: f32,
+    shutter: (f32, f32),
+    projection: Projection,
+    exposure: f32,
+    aperture_shape: ApertureShape,
+    /// The point of the image to focus on
+    autofocus: Option<(f32, f32)>,
+    vfov: f32,
+    aspect_r: f32,
     u: Vector,
     v: Vector,
     w: Vector,
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct CameraConstructor {
     pub look_from: Vector,
     pub look_at: Vector,
@@ -38,6 +145,10 @@ pub struct CameraConstructor {
     pub aspect_r: f32,
     pub aperture: f32,
     pub focus_dist: f32,
+    /// The times at which the shutter opens and closes. Rays are spread
+    /// evenly over this interval.
+    pub shutter: (f32, f32),
+    pub projection: Projection,
 }
 
 impl Camera {
@@ -62,24 +173,217 @@ impl Camera {
             vertical: 2.0 * half_height * cam.focus_dist * v,
             origin: cam.look_from,
             lens_radius: cam.aperture / 2.0,
+            focus_dist: cam.focus_dist,
+            shutter: cam.shutter,
+            projection: cam.projection,
+            exposure: 1.0,
+            aperture_shape: ApertureShape::Circle,
+            autofocus: None,
+            vfov: cam.vfov,
+            aspect_r: cam.aspect_r,
+        }
+    }
+
+    /// Sets how much the light reaching the camera is scaled by before
+    /// tone mapping
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    pub fn with_aperture_shape(mut self, shape: ApertureShape) -> Self {
+        self.aperture_shape = shape;
+        self
+    }
+
+    /// Focuses on whatever is seen through the point `(u, v)` of the
+    /// image once the camera is placed in a [`Scene`], instead of at
+    /// `focus_dist`
+    ///
+    /// [`Scene`]: ../config/struct.Scene.html
+    pub fn with_autofocus(mut self, u: f32, v: f32) -> Self {
+        self.autofocus = Some((u, v));
+        self
+    }
+
+    /// The ray through the autofocus point from the middle of the lens,
+    /// if the camera has one
+    pub fn autofocus_ray(&self) -> Option<Ray> {
+        let (u, v) = self.autofocus?;
+        let (origin, focus) = self.pinhole(u, v)?;
+
+        Some(Ray {
+            origin: self.origin + origin,
+            dir: focus - origin,
+            time: self.shutter.0,
+        })
+    }
+
+    /// Moves the focus to the distance of `p`, measured along the view
+    /// direction for flat projections and straight out from the camera
+    /// for round ones
+    pub fn focus_on(&mut self, p: Vector) {
+        let to_p = p - self.origin;
+        let dist = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                -to_p.dot(self.w)
+            }
+            Projection::Fisheye(_) | Projection::Equirectangular => {
+                to_p.length()
+            }
+        };
+
+        if dist <= 0.0 {
+            return;
         }
+
+        let scale = dist / self.focus_dist;
+        self.lower_left_corner =
+            self.origin + (self.lower_left_corner - self.origin) * scale;
+        self.horizontal *= scale;
+        self.vertical *= scale;
+        self.focus_dist = dist;
     }
 }
 
 impl Camera {
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
-        let rd = self.lens_radius * random_in_unit_disk();
-        let offset = self.u * rd.x + self.v * rd.y;
-
-        Ray {
-            origin: self.origin + offset,
-            dir: self.lower_left_corner
-                + self.horizontal * u
-                + self.vertical * v
-                - self.origin
-                - offset,
+    /// The ray through the point `(u, v)` of the image, where both run
+    /// from 0 to 1. Returns `None` for points that the projection doesn't
+    /// cover, like the corners outside of a fisheye circle.
+    pub fn get_ray(&self, u: f32, v: f32) -> Option<Ray> {
+        let (origin, focus) = self.pinhole(u, v)?;
+
+        let (lens_x, lens_y) = self.aperture_shape.sample();
+        let offset = (self.u * lens_x + self.v * lens_y) * self.lens_radius;
+        let (open, close) = self.shutter;
+
+        Some(Ray {
+            origin: self.origin + origin + offset,
+            dir: focus - origin - offset,
+            time: open + random::<f32>() * (close - open),
+        })
+    }
+
+    /// Where the ray through `(u, v)` starts relative to the camera when
+    /// the lens is a pinhole, and the point it reaches at the focus
+    /// distance
+    fn pinhole(&self, u: f32, v: f32) -> Option<(Vector, Vector)> {
+        let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        let (origin, focus) = match self.projection {
+            Projection::Perspective => (
+                Vector::zeros(),
+                self.lower_left_corner
+                    + self.horizontal * u
+                    + self.vertical * v
+                    - self.origin,
+            ),
+            Projection::Orthographic { height } => {
+                let origin = self.u * x * height * self.aspect_r / 2.0
+                    + self.v * y * height / 2.0;
+
+                (origin, origin - self.w * self.focus_dist)
+            }
+            Projection::Fisheye(mapping) => {
+                let x = x * self.aspect_r;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+
+                let theta_max = self.vfov.to_radians() / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * theta_max,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (r * (theta_max / 2.0).sin()).asin()
+                    }
+                };
+
+                let (cos_phi, sin_phi) =
+                    if r > 0.0 { (x / r, y / r) } else { (1.0, 0.0) };
+                let dir = (self.u * cos_phi + self.v * sin_phi) * theta.sin()
+                    - self.w * theta.cos();
+
+                (Vector::zeros(), dir * self.focus_dist)
+            }
+            Projection::Equirectangular => {
+                let phi = x * f32::consts::PI;
+                let theta = y * f32::consts::FRAC_PI_2;
+                let dir = (self.u * phi.sin() - self.w * phi.cos())
+                    * theta.cos()
+                    + self.v * theta.sin();
+
+                (Vector::zeros(), dir * self.focus_dist)
+            }
+        };
+
+        Some((origin, focus))
+    }
+}
+
+/// A camera described like a real one. The field of view comes from the
+/// focal length and the sensor, the size of the aperture from the
+/// f-stop, and the brightness of the image from the f-stop, shutter
+/// speed and ISO, so that scenes lit in physical units look like photos
+/// taken with the same settings.
+#[derive(Debug, Copy, Clone)]
+pub struct PhysicalCamera {
+    pub look_from: Vector,
+    pub look_at: Vector,
+    pub vup: Vector,
+    /// In millimetres
+    pub focal_length: f32,
+    /// Width and height of the sensor in millimetres, like (36, 24) for a
+    /// full frame camera
+    pub sensor: (f32, f32),
+    pub f_stop: f32,
+    /// How long the shutter is open for in seconds, which also sets how
+    /// much moving objects are blurred
+    pub shutter_speed: f32,
+    pub iso: f32,
+    pub focus_dist: f32,
+    /// How many scene units make up a metre, to size the aperture
+    pub units_per_metre: f32,
+}
+
+impl PhysicalCamera {
+    /// The vertical field of view in degrees
+    pub fn vfov(&self) -> f32 {
+        (2.0 * (self.sensor.1 / (2.0 * self.focal_length)).atan()).to_degrees()
+    }
+
+    /// The diameter of the aperture in scene units
+    pub fn aperture(&self) -> f32 {
+        self.focal_length / self.f_stop / 1000.0 * self.units_per_metre
+    }
+
+    /// The factor that scene luminance in cd/m^2 is multiplied by. A
+    /// luminance of 1 / exposure just saturates the sensor, following
+    /// the saturation based sensitivity in ISO 12232.
+    pub fn exposure(&self) -> f32 {
+        self.shutter_speed * self.iso / (120.0 * self.f_stop * self.f_stop)
+    }
+
+    pub fn constructor(&self) -> CameraConstructor {
+        CameraConstructor {
+            look_from: self.look_from,
+            look_at: self.look_at,
+            vup: self.vup,
+            vfov: self.vfov(),
+            aspect_r: self.sensor.0 / self.sensor.1,
+            aperture: self.aperture(),
+            focus_dist: self.focus_dist,
+            shutter: (0.0, self.shutter_speed),
+            projection: Projection::Perspective,
         }
     }
+
+    pub fn camera(&self) -> Camera {
+        Camera::new(self.constructor()).with_exposure(self.exposure())
+    }
 }
 
 impl Default for Camera {
@@ -92,6 +396,64 @@ impl Default for Camera {
             aspect_r: (16.0 / 9.0),
             aperture: 0.0,
             focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
         })
     }
 }
+
+/// A camera whose position, target and field of view follow keyframes.
+/// Everything else comes from `base`, whose shutter interval is taken
+/// relative to the time the camera is placed at.
+#[derive(Debug)]
+pub struct CameraAnimation {
+    pub look_from: Keyframes<Vector>,
+    pub look_at: Keyframes<Vector>,
+    pub vfov: Keyframes<f32>,
+    pub base: CameraConstructor,
+    /// See [`Camera::with_exposure`]
+    ///
+    /// [`Camera::with_exposure`]: struct.Camera.html#method.with_exposure
+    pub exposure: f32,
+    pub aperture_shape: ApertureShape,
+    /// See [`Camera::with_autofocus`]. The focus follows whatever is at
+    /// this point of every frame.
+    ///
+    /// [`Camera::with_autofocus`]: struct.Camera.html#method.with_autofocus
+    pub autofocus: Option<(f32, f32)>,
+}
+
+impl CameraAnimation {
+    /// Creates keyframes that keep the camera still, to be replaced with
+    /// the ones that should move
+    pub fn still(base: CameraConstructor) -> Self {
+        Self {
+            look_from: Keyframes::constant(base.look_from),
+            look_at: Keyframes::constant(base.look_at),
+            vfov: Keyframes::constant(base.vfov),
+            base,
+            exposure: 1.0,
+            aperture_shape: ApertureShape::Circle,
+            autofocus: None,
+        }
+    }
+
+    pub fn at(&self, time: f32) -> Camera {
+        let (open, close) = self.base.shutter;
+
+        let camera = Camera::new(CameraConstructor {
+            look_from: self.look_from.at(time),
+            look_at: self.look_at.at(time),
+            vfov: self.vfov.at(time),
+            shutter: (time + open, time + close),
+            ..self.base
+        })
+        .with_exposure(self.exposure)
+        .with_aperture_shape(self.aperture_shape.clone());
+
+        match self.autofocus {
+            Some((u, v)) => camera.with_autofocus(u, v),
+            None => camera,
+        }
+    }
+}
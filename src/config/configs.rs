@@ -14,14 +14,27 @@
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
 use crate::{
-    bvh::Bvh,
-    camera::{Camera, CameraConstructor},
+    aabb::Aabb,
+    background::{Background, EnvironmentMap, Sky},
+    camera::{
+        ApertureShape, Camera, CameraAnimation, CameraConstructor,
+        FisheyeMapping, PhysicalCamera, Projection,
+    },
     color::ToneMappingOperator,
-    config::{Config, Scene},
-    materials::Material,
+    config::{Animation, Config, Scene},
+    image::Image,
+    keyframes::{Interpolation, Keyframes},
+    lights::{IesProfile, Light},
+    materials::{Emitter, Material},
+    media::{Medium, VoxelGrid},
+    obj_loader::ObjLoader,
     object::Object,
-    primitives::{Block, Primitive, RectPlane, Rectangle, Sphere},
-    stl_loader::StlLoader,
+    primitives::{
+        Block, Cone, Csg, CsgOperation, Curve, CurveType, Cylinder, Disk,
+        Heightfield, Motion, MovingSphere, Plane, Polygon, Primitive, Quad,
+        RectPlane, Rectangle, Sdf, SdfShape, Sphere, Torus, Transformation,
+    },
+    stl_loader::{Anchor, AxisConversion, StlLoader, StlNormals, StlOptions},
     vector3::Vector,
 };
 
@@ -34,73 +47,82 @@ const R_1600: (u16, u16) = (1600, 900);
 #[allow(dead_code)]
 const R_1920: (u16, u16) = (1920, 1080);
 
-use std::{fs, io};
+use rand::prelude::*;
+use std::{fs, io, sync::Arc};
 
-// #[allow(dead_code)]
-// pub fn config_stl_test() -> Config {
-//     let mut file = fs::File::open("test/squirtle_starter_1gen_flowalistik.STL")
-//         .map(io::BufReader::new)
-//         .unwrap();
-
-//     let mut objects: Vec<Object> = StlLoader::parse(&mut file)
-//         .into_iter()
-//         .map(|t| Object {
-//             primitive: t,
-//             transformation: None,
-//             material: Material::Diffuse(Vector::new(0.9, 0.1, 0.1)),
-//         })
-//         .collect();
-
-//     objects.push(Object {
-//         primitive: Primitive::Rectangle(Rectangle::new(
-//             -100_000.0,
-//             100_000.0,
-//             -100_000.0,
-//             100_000.0,
-//             -1.0,
-//             1.0,
-//             RectPlane::XY,
-//         )),
-//         transformation: None,
-//         material: Material::Diffuse(Vector::new(0.73, 0.73, 0.73)),
-//     });
-
-//     objects.push(Object {
-//         primitive: Primitive::Block(Block::new(
-//             Vector::new(90.0, -90.0, 0.0),
-//             Vector::new(110.0, -110.0, 20.0),
-//         )),
-//         transformation: None,
-//         material: Material::Dielectric(1.52),
-//     });
-
-//     objects.push(Object {
-//         primitive: Primitive::Sphere(Sphere::new(
-//             Vector::new(20.0, 0.0, 120.0),
-//             15.0,
-//         )),
-//         transformation: None,
-//         material: Material::Light(Vector::new(15.0, 14.0, 12.0)),
-//     });
+#[allow(dead_code)]
+pub fn config_stl_test() -> Config {
+    let mut file = fs::File::open("test/squirtle_starter_1gen_flowalistik.STL")
+        .map(io::BufReader::new)
+        .unwrap();
 
-//     Config {
-//         resolution: R_480,
-//         samples: 300,
-//         tmo: ToneMappingOperator::ReinhardJodie,
-//         scene: Scene {
-//             objects: Bvh::new(objects),
-//             camera: Camera::new(CameraConstructor {
-//                 look_at: Vector::new(-90.0, 10.0, 30.0),
-//                 look_from: Vector::new(120.0, -60.0, 20.0),
-//                 vup: Vector::new(0.0, 0.0, 1.0),
-//                 vfov: 32.0,
-//                 aspect_r: 16.0 / 9.0,
-//                 aperture: 0.0,
-//                 focus_dist: 1.0,
-//             }),
-//         },
-//     }
-// }
+    // The model is inside out, with its facets wound clockwise
+    let options = StlOptions {
+        normals: StlNormals::Flip,
+        ..StlOptions::default()
+    };
+    let stl = StlLoader::parse(&mut file, options).unwrap();
+    eprintln!("Loaded STL: {}", stl.stats);
+
+    let mut objects: Vec<Object> = stl
+        .triangles()
+        .into_iter()
+        .map(|t| Object {
+            primitive: t,
+            transformation: None,
+            material: Material::Diffuse(Vector::new(0.9, 0.1, 0.1)),
+        })
+        .collect();
+
+    objects.push(Object {
+        primitive: Primitive::Plane(Plane::new(
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(0.0, 0.0, 1.0),
+            50.0,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.73, 0.73, 0.73)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Block(Block::new(
+            Vector::new(90.0, -90.0, 0.0),
+            Vector::new(110.0, -110.0, 20.0),
+        )),
+        transformation: None,
+        material: Material::Dielectric(1.52),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(
+            Vector::new(20.0, 0.0, 120.0),
+            15.0,
+        )),
+        transformation: None,
+        material: Material::Light(Emitter::new(Vector::new(15.0, 14.0, 12.0))),
+    });
+
+    Config {
+        resolution: R_480,
+        samples: 300,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_at: Vector::new(-90.0, 10.0, 30.0),
+                look_from: Vector::new(120.0, -60.0, 20.0),
+                vup: Vector::new(0.0, 0.0, 1.0),
+                vfov: 32.0,
+                aspect_r: 16.0 / 9.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
 
 // #[allow(dead_code)]
 // pub fn config_test_ball() -> Config {
@@ -356,6 +378,597 @@ use std::{fs, io};
 //     }
 // }
 
+/// A few spheres outside under a sky, lit only by the environment
+#[allow(dead_code)]
+pub fn config_environment() -> Config {
+    let sky =
+        Image::from_file("test/sky.hdr").unwrap_or_else(|e| panic!("{}", e));
+
+    let objects = outdoor_spheres();
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(0.0, 2.5, -9.0),
+            look_at: Vector::new(0.0, 0.8, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 35.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    scene.background = Some(Background::Environment(EnvironmentMap::new(
+        sky, 150.0, 1.0,
+    )));
+
+    Config {
+        resolution: R_480,
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// The spheres from `config_environment` under an afternoon sun
+#[allow(dead_code)]
+pub fn config_sky() -> Config {
+    let objects = outdoor_spheres();
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(0.0, 2.5, -9.0),
+            look_at: Vector::new(0.0, 0.8, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 35.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    scene.background = Some(Background::Sky(Sky::new(30.0, 200.0, 3.0, 1.0)));
+
+    Config {
+        resolution: R_480,
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// The spheres from `config_environment` at night, lit by a lamp, a
+/// spotlight and the moon
+#[allow(dead_code)]
+pub fn config_lights() -> Config {
+    let objects = outdoor_spheres();
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(0.0, 2.5, -9.0),
+            look_at: Vector::new(0.0, 0.8, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 35.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    scene.lights = vec![
+        Light::Point {
+            position: Vector::new(-1.1, 2.5, -1.5),
+            intensity: Vector::new(6.0, 4.5, 3.0),
+            profile: None,
+        },
+        Light::Spot {
+            position: Vector::new(3.0, 5.0, -2.0),
+            direction: Vector::new(-3.0, -4.0, 2.0),
+            intensity: Vector::new(40.0, 40.0, 45.0),
+            angle: 20.0,
+            falloff: 15.0,
+            profile: None,
+        },
+        Light::Directional {
+            direction: Vector::new(1.0, -1.0, 2.0),
+            irradiance: Vector::new(0.05, 0.06, 0.1),
+        },
+    ];
+
+    Config {
+        resolution: R_480,
+        samples: 100,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// The spheres from `config_environment` under two fixtures with an IES
+/// profile, a downlight and a ceiling panel
+#[allow(dead_code)]
+pub fn config_ies() -> Config {
+    let profile = match IesProfile::from_file("test/downlight.ies") {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // The panel gives off 20 lumens per square unit, the downlight 30
+    // lumens in total
+    let panel = Arc::new(profile.clone().scaled_to_lumens(20.0));
+    let downlight = Arc::new(profile.scaled_to_lumens(30.0));
+
+    let mut objects = outdoor_spheres();
+    objects.push(Object {
+        primitive: Primitive::Rectangle(Rectangle::new(
+            -0.5,
+            2.7,
+            -0.5,
+            0.5,
+            4.0,
+            -1.0,
+            RectPlane::XZ,
+        )),
+        transformation: None,
+        material: Material::Light(Emitter {
+            profile: Some(panel),
+            ..Emitter::new(Vector::new(0.9, 0.95, 1.0))
+        }),
+    });
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(0.0, 2.5, -9.0),
+            look_at: Vector::new(0.0, 0.8, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 35.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    scene.lights = vec![Light::Point {
+        position: Vector::new(-2.2, 3.5, 0.0),
+        intensity: Vector::new(1.0, 0.8, 0.6),
+        profile: Some(downlight),
+    }];
+
+    Config {
+        resolution: R_480,
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// The spheres from `config_environment` at night in front of a TV, lit
+/// by a two-sided panel and a string of small colored lights
+#[allow(dead_code)]
+pub fn config_emitters() -> Config {
+    let tv = match Image::from_ppm("test/tv.ppm") {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut objects = outdoor_spheres();
+    objects.push(Object {
+        primitive: Primitive::Quad(Quad::new(
+            Vector::new(3.2, 0.3, 3.0),
+            Vector::new(-6.4, 0.0, 0.0),
+            Vector::new(0.0, 3.6, 0.0),
+        )),
+        transformation: None,
+        material: Material::Light(Emitter {
+            texture: Some(Arc::new(tv)),
+            ..Emitter::new(Vector::new(1.5, 1.5, 1.5))
+        }),
+    });
+    objects.push(Object {
+        primitive: Primitive::Rectangle(Rectangle::new(
+            0.0,
+            2.0,
+            -2.0,
+            1.0,
+            -4.5,
+            1.0,
+            RectPlane::YZ,
+        )),
+        transformation: None,
+        material: Material::Light(Emitter {
+            two_sided: true,
+            ..Emitter::new(Vector::new(1.0, 0.9, 0.8))
+        }),
+    });
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(0.0, 2.5, -9.0),
+            look_at: Vector::new(0.0, 1.2, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    // Lights hung in a sagging line across the scene
+    let colors = [
+        Vector::new(1.0, 0.3, 0.2),
+        Vector::new(1.0, 0.8, 0.2),
+        Vector::new(0.3, 1.0, 0.3),
+        Vector::new(0.2, 0.5, 1.0),
+    ];
+    scene.lights = (0..24)
+        .map(|n| {
+            let x = -4.0 + 8.0 * n as f32 / 23.0;
+            Light::Point {
+                position: Vector::new(x, 2.6 + 0.06 * x * x, -1.5),
+                intensity: colors[n % colors.len()] * 0.5,
+                profile: None,
+            }
+        })
+        .collect();
+
+    Config {
+        resolution: R_480,
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// The spheres from `config_environment` under two real 800 lumen
+/// downlights, photographed with settings for a dim room. Scene units are
+/// metres.
+#[allow(dead_code)]
+pub fn config_photographic() -> Config {
+    let profile = match IesProfile::from_file("test/downlight.ies") {
+        Ok(profile) => Arc::new(profile.scaled_to_lumens(800.0)),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let camera = PhysicalCamera {
+        look_from: Vector::new(0.0, 1.6, -7.0),
+        look_at: Vector::new(0.0, 0.9, 0.0),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        focal_length: 35.0,
+        sensor: (36.0, 24.0),
+        f_stop: 2.0,
+        shutter_speed: 1.0 / 30.0,
+        iso: 3200.0,
+        focus_dist: 7.0,
+        units_per_metre: 1.0,
+    };
+
+    let mut scene = Scene::new(outdoor_spheres(), camera.camera());
+
+    scene.lights = [-2.2, 2.2]
+        .iter()
+        .map(|&x| Light::Point {
+            position: Vector::new(x, 3.0, 0.0),
+            intensity: Vector::new(1.0, 0.9, 0.8),
+            profile: Some(Arc::clone(&profile)),
+        })
+        .collect();
+
+    Config {
+        resolution: (480, 320),
+        samples: 100,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// A city at night with thousands of lit windows and street lights,
+/// which needs the light BVH to find the lights near each point
+#[allow(dead_code)]
+pub fn config_city() -> Config {
+    // Seeded so that the city looks the same on every render
+    let mut rng = StdRng::seed_from_u64(7);
+
+    let mut objects = vec![Object {
+        primitive: Primitive::Plane(Plane::new(
+            Vector::zeros(),
+            Vector::new(0.0, 1.0, 0.0),
+            10.0,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.1, 0.1, 0.1)),
+    }];
+    let mut lights = Vec::new();
+
+    let (blocks, spacing, width) = (12, 3.0, 2.0);
+    let window = (0.3, 0.35);
+    let offset = 0.01;
+
+    for i in 0..blocks {
+        for j in 0..blocks {
+            let x0 = (i as f32 - blocks as f32 / 2.0) * spacing;
+            let z0 = (j as f32 - blocks as f32 / 2.0) * spacing;
+            let (x1, z1) = (x0 + width, z0 + width);
+            let height = rng.gen_range(2.0, 10.0);
+
+            objects.push(Object {
+                primitive: Primitive::Block(Block::new(
+                    Vector::new(x0, 0.0, z0),
+                    Vector::new(x1, height, z1),
+                )),
+                transformation: None,
+                material: Material::Diffuse(Vector::new(0.3, 0.3, 0.32)),
+            });
+
+            // Rows of windows up each of the four sides
+            let mut y = 0.5;
+            while y + window.1 < height - 0.2 {
+                for col in 0..3 {
+                    let a = 0.25 + col as f32 * 0.6;
+
+                    for side in 0..4 {
+                        if rng.gen::<f32>() > 0.3 {
+                            continue;
+                        }
+
+                        let color = Vector::new(
+                            3.0,
+                            rng.gen_range(2.0, 2.6),
+                            rng.gen_range(1.0, 2.0),
+                        );
+                        let (y0, y1) = (y, y + window.1);
+
+                        let rect = match side {
+                            0 => Rectangle::new(
+                                x0 + a,
+                                x0 + a + window.0,
+                                y0,
+                                y1,
+                                z0 - offset,
+                                -1.0,
+                                RectPlane::XY,
+                            ),
+                            1 => Rectangle::new(
+                                x0 + a,
+                                x0 + a + window.0,
+                                y0,
+                                y1,
+                                z1 + offset,
+                                1.0,
+                                RectPlane::XY,
+                            ),
+                            2 => Rectangle::new(
+                                y0,
+                                y1,
+                                z0 + a,
+                                z0 + a + window.0,
+                                x0 - offset,
+                                -1.0,
+                                RectPlane::YZ,
+                            ),
+                            _ => Rectangle::new(
+                                y0,
+                                y1,
+                                z0 + a,
+                                z0 + a + window.0,
+                                x1 + offset,
+                                1.0,
+                                RectPlane::YZ,
+                            ),
+                        };
+
+                        objects.push(Object {
+                            primitive: Primitive::Rectangle(rect),
+                            transformation: None,
+                            material: Material::Light(Emitter::new(color)),
+                        });
+                    }
+                }
+
+                y += 0.6;
+            }
+
+            // A street light on the corner of every block
+            lights.push(Light::Point {
+                position: Vector::new(x0 - 0.5, 0.8, z0 - 0.5),
+                intensity: Vector::new(0.8, 0.5, 0.2),
+                profile: None,
+            });
+        }
+    }
+
+    lights.push(Light::Directional {
+        direction: Vector::new(-1.0, -2.0, 1.0),
+        irradiance: Vector::new(0.01, 0.012, 0.02),
+    });
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(-22.0, 14.0, -26.0),
+            look_at: Vector::new(0.0, 0.0, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aspect_r: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    scene.lights = lights;
+
+    Config {
+        resolution: R_480,
+        samples: 64,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// A 360 degree panorama of `config_city` from above one of the streets
+#[allow(dead_code)]
+pub fn config_city_panorama() -> Config {
+    let mut config = config_city();
+
+    config.resolution = (960, 480);
+    config.scene.camera = Camera::new(CameraConstructor {
+        look_from: Vector::new(-1.5, 3.0, -0.5),
+        look_at: Vector::new(-1.5, 3.0, 10.0),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        vfov: 180.0,
+        aspect_r: 2.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter: (0.0, 0.0),
+        projection: Projection::Equirectangular,
+    });
+
+    config
+}
+
+/// `config_city` looking straight up from the middle of a crossroads
+/// through a 180 degree fisheye lens
+#[allow(dead_code)]
+pub fn config_city_fisheye() -> Config {
+    let mut config = config_city();
+
+    config.resolution = (480, 480);
+    config.scene.camera = Camera::new(CameraConstructor {
+        look_from: Vector::new(-0.5, 0.5, -0.5),
+        look_at: Vector::new(-0.5, 10.0, -0.5),
+        vup: Vector::new(0.0, 0.0, 1.0),
+        vfov: 180.0,
+        aspect_r: 1.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter: (0.0, 0.0),
+        projection: Projection::Fisheye(FisheyeMapping::Equisolid),
+    });
+
+    config
+}
+
+/// `config_city` looking down a street from head height through a 220
+/// degree fisheye lens, wide enough to see the road behind the camera
+#[allow(dead_code)]
+pub fn config_city_fisheye_street() -> Config {
+    let mut config = config_city();
+
+    config.resolution = (480, 480);
+    config.scene.camera = Camera::new(CameraConstructor {
+        look_from: Vector::new(-0.5, 1.0, -15.5),
+        look_at: Vector::new(-0.5, 1.0, 0.0),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        vfov: 220.0,
+        aspect_r: 1.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter: (0.0, 0.0),
+        projection: Projection::Fisheye(FisheyeMapping::Equidistant),
+    });
+
+    config
+}
+
+/// `config_city` from just outside one corner through a wide open lens
+/// with six blades, focused on the closest building
+#[allow(dead_code)]
+pub fn config_city_bokeh() -> Config {
+    let mut config = config_city();
+
+    config.scene.camera = Camera::new(CameraConstructor {
+        look_from: Vector::new(-21.0, 3.0, -21.0),
+        look_at: Vector::new(2.0, 5.0, -6.0),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        vfov: 40.0,
+        aspect_r: 16.0 / 9.0,
+        aperture: 1.2,
+        focus_dist: 1.0,
+        shutter: (0.0, 0.0),
+        projection: Projection::Perspective,
+    })
+    .with_aperture_shape(ApertureShape::Polygon {
+        blades: 6,
+        rotation: 15.0,
+    })
+    .with_autofocus(0.25, 0.4);
+    config.scene.autofocus();
+
+    config
+}
+
+/// `config_city_bokeh` through a star shaped aperture
+#[allow(dead_code)]
+pub fn config_city_bokeh_star() -> Config {
+    let star = match Image::from_ppm("test/aperture_star.pgm") {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = config_city_bokeh();
+    config.scene.camera = config
+        .scene
+        .camera
+        .clone()
+        .with_aperture_shape(ApertureShape::from_image(&star));
+
+    config
+}
+
+/// An isometric view of `config_city`, with no perspective
+#[allow(dead_code)]
+pub fn config_city_isometric() -> Config {
+    let mut config = config_city();
+
+    config.scene.camera = Camera::new(CameraConstructor {
+        look_from: Vector::new(-40.0, 40.0 * f32::sqrt(2.0 / 3.0), -40.0),
+        look_at: Vector::new(-1.5, 0.0, -1.5),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        vfov: 40.0,
+        aspect_r: 16.0 / 9.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        shutter: (0.0, 0.0),
+        projection: Projection::Orthographic { height: 28.0 },
+    });
+
+    config
+}
+
 #[allow(dead_code)]
 pub fn config_cornell_box() -> Config {
     let objects = cornell_box(555.0);
@@ -364,9 +977,715 @@ pub fn config_cornell_box() -> Config {
         resolution: (320, 320),
         samples: 200,
         tmo: ToneMappingOperator::ReinhardJodie,
-        scene: Scene {
-            objects: Bvh::new(objects),
-            camera: Camera::new(CameraConstructor {
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+#[allow(dead_code)]
+pub fn config_cornell_box_stl() -> Config {
+    let size = 555.0;
+    let mut file = fs::File::open("test/squirtle_starter_1gen_flowalistik.STL")
+        .map(io::BufReader::new)
+        .unwrap();
+
+    // The model is inside out, with its facets wound clockwise
+    let stl = StlLoader::parse(
+        &mut file,
+        StlOptions {
+            normals: StlNormals::Flip,
+            up_axis: AxisConversion::ZUpToYUp,
+            anchor: Anchor::Base,
+            fit_size: Some(230.0),
+            offset: Vector::new(390.0, 0.0, 250.0),
+        },
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    eprintln!("Loaded STL: {}", stl.stats);
+
+    let mut objects = cornell_box(size);
+    objects.push(Object {
+        primitive: Primitive::Mesh(stl.mesh()),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.2, 0.45, 0.75)),
+    });
+
+    // The vase is modelled with +Z up, so treating it as +Y up turns it
+    // upside down onto its rim
+    let mut file = fs::File::open("test/sotvl_Spiral-Vase.stl")
+        .map(io::BufReader::new)
+        .unwrap();
+
+    let vase = StlLoader::parse(
+        &mut file,
+        StlOptions {
+            normals: StlNormals::Recompute,
+            up_axis: AxisConversion::YUpToZUp,
+            anchor: Anchor::Center,
+            fit_size: Some(180.0),
+            offset: Vector::new(150.0, 90.0, 300.0),
+        },
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    eprintln!("Loaded STL: {}", vase.stats);
+
+    objects.push(Object {
+        primitive: Primitive::Mesh(vase.mesh()),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.75, 0.45, 0.2)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// A cylinder, a cone and a mirrored torus standing on a round rug
+#[allow(dead_code)]
+pub fn config_cornell_box_quadrics() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    objects.push(Object {
+        primitive: Primitive::Disk(Disk::new(
+            Vector::new(278.0, 0.5, 260.0),
+            Vector::new(0.0, 1.0, 0.0),
+            200.0,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.6, 0.5, 0.3)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Cylinder(Cylinder::new(
+            Vector::new(150.0, 0.0, 380.0),
+            Vector::new(150.0, 220.0, 380.0),
+            60.0,
+            true,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.2, 0.45, 0.75)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Cone(Cone::new(
+            Vector::new(400.0, 0.0, 400.0),
+            Vector::new(400.0, 260.0, 400.0),
+            80.0,
+            true,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.75, 0.6, 0.2)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Torus(Torus::new(
+            Vector::new(270.0, 62.0, 200.0),
+            Vector::new(0.3, 1.0, -0.6),
+            70.0,
+            22.0,
+        )),
+        transformation: None,
+        material: Material::Reflector(Vector::new(0.8, 0.8, 0.8)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// A star-shaped neon sign glowing on the back wall behind a sphere
+#[allow(dead_code)]
+pub fn config_cornell_box_neon() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    // Wound clockwise as seen from the camera so that it faces the room
+    let star = (0..10)
+        .map(|i| {
+            let angle = std::f32::consts::FRAC_PI_2
+                - i as f32 * std::f32::consts::PI / 5.0;
+            let radius = if i % 2 == 0 { 150.0 } else { 60.0 };
+            Vector::new(
+                278.0 + radius * angle.cos(),
+                300.0 + radius * angle.sin(),
+                size - 1.0,
+            )
+        })
+        .collect();
+
+    objects.push(Object {
+        primitive: Primitive::Polygon(Polygon::new(star)),
+        transformation: None,
+        material: Material::Light(Emitter::new(Vector::new(6.0, 2.0, 4.0))),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(
+            Vector::new(278.0, 90.0, 250.0),
+            90.0,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.73, 0.73, 0.73)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// Two solids built with CSG: the classic rounded cube with holes
+/// drilled along each axis, and a sphere with a wedge cut out of it
+#[allow(dead_code)]
+pub fn config_cornell_box_csg() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    let center = Vector::new(170.0, 80.0, 300.0);
+    let half = Vector::new(80.0, 80.0, 80.0);
+    let rounded = Csg::new(
+        CsgOperation::Intersection,
+        Primitive::Block(Block::new(center - half, center + half)),
+        Primitive::Sphere(Sphere::new(center, 105.0)),
+    );
+
+    let drill = |axis: Vector| {
+        Primitive::Cylinder(Cylinder::new(
+            center - axis * 100.0,
+            center + axis * 100.0,
+            45.0,
+            true,
+        ))
+    };
+    let holes = Csg::new(
+        CsgOperation::Union,
+        Primitive::Csg(Csg::new(
+            CsgOperation::Union,
+            drill(Vector::new(1.0, 0.0, 0.0)),
+            drill(Vector::new(0.0, 1.0, 0.0)),
+        )),
+        drill(Vector::new(0.0, 0.0, 1.0)),
+    );
+
+    objects.push(Object {
+        primitive: Primitive::Csg(Csg::new(
+            CsgOperation::Difference,
+            Primitive::Csg(rounded),
+            Primitive::Csg(holes),
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.2, 0.45, 0.75)),
+    });
+
+    // The cut faces the camera so the inside of the sphere shows
+    objects.push(Object {
+        primitive: Primitive::Csg(Csg::new(
+            CsgOperation::Difference,
+            Primitive::Sphere(Sphere::new(
+                Vector::new(400.0, 90.0, 250.0),
+                90.0,
+            )),
+            Primitive::Block(Block::new(
+                Vector::new(400.0, 90.0, 150.0),
+                Vector::new(500.0, 190.0, 250.0),
+            )),
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.75, 0.6, 0.2)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// Sphere traced distance fields: a Mandelbulb, a ring resting in a
+/// rounded box, and two spheres blended into a peanut with a flat bottom
+#[allow(dead_code)]
+pub fn config_cornell_box_sdf() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    let bulb_center = Vector::new(390.0, 115.0, 330.0);
+    let bulb_scale = 120.0;
+    let reach = Vector::new(bulb_scale, bulb_scale, bulb_scale);
+
+    objects.push(Object {
+        primitive: Primitive::Sdf(Sdf::new(
+            SdfShape::Mandelbulb {
+                center: bulb_center,
+                scale: bulb_scale,
+                power: 8.0,
+                iterations: 8,
+            },
+            Aabb::new(bulb_center - reach, bulb_center + reach),
+            256,
+            0.05,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.75, 0.6, 0.2)),
+    });
+
+    // A ring sitting in a smooth dent on top of a rounded box
+    let center = Vector::new(150.0, 70.0, 260.0);
+    let dented = SdfShape::SmoothSubtract(
+        Box::new(SdfShape::RoundBox {
+            center,
+            half_extents: Vector::new(70.0, 70.0, 70.0),
+            radius: 12.0,
+        }),
+        Box::new(SdfShape::Sphere {
+            center: center + Vector::new(0.0, 80.0, 0.0),
+            radius: 45.0,
+        }),
+        10.0,
+    );
+    let ring = SdfShape::Torus {
+        center: center + Vector::new(0.0, 68.0, 0.0),
+        major_radius: 38.0,
+        minor_radius: 10.0,
+    };
+
+    objects.push(Object {
+        primitive: Primitive::Sdf(Sdf::new(
+            SdfShape::Union(Box::new(dented), Box::new(ring)),
+            Aabb::new(
+                center - Vector::new(71.0, 71.0, 71.0),
+                center + Vector::new(71.0, 90.0, 71.0),
+            ),
+            256,
+            0.05,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.2, 0.45, 0.75)),
+    });
+
+    // Two spheres melted together, sliced flat at the floor and with a
+    // notch cut out of the top
+    let peanut = SdfShape::SmoothUnion(
+        Box::new(SdfShape::Sphere {
+            center: Vector::new(250.0, 30.0, 120.0),
+            radius: 40.0,
+        }),
+        Box::new(SdfShape::Sphere {
+            center: Vector::new(320.0, 30.0, 120.0),
+            radius: 35.0,
+        }),
+        25.0,
+    );
+    let sliced = SdfShape::Intersection(
+        Box::new(peanut),
+        Box::new(SdfShape::Box {
+            center: Vector::new(285.0, 40.0, 120.0),
+            half_extents: Vector::new(100.0, 40.0, 60.0),
+        }),
+    );
+    let notched = SdfShape::Subtract(
+        Box::new(sliced),
+        Box::new(SdfShape::Box {
+            center: Vector::new(285.0, 80.0, 120.0),
+            half_extents: Vector::new(8.0, 30.0, 60.0),
+        }),
+    );
+
+    objects.push(Object {
+        primitive: Primitive::Sdf(Sdf::new(
+            notched,
+            Aabb::new(
+                Vector::new(205.0, 0.0, 75.0),
+                Vector::new(360.0, 80.0, 165.0),
+            ),
+            256,
+            0.05,
+        )),
+        transformation: None,
+        material: Material::Reflector(Vector::new(0.8, 0.8, 0.8)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// Rolling hills made from fractal noise along the back of the box, and
+/// a star embossed into a tile on the floor in front of them
+#[allow(dead_code)]
+pub fn config_cornell_box_terrain() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    let star = match Image::from_ppm("test/aperture_star.pgm") {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    objects.push(Object {
+        primitive: Primitive::Heightfield(Heightfield::from_noise(
+            (128, 128),
+            5,
+            7,
+            Vector::new(0.0, 0.0, 300.0),
+            Vector::new(size, 120.0, size - 300.0),
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.35, 0.45, 0.2)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Heightfield(Heightfield::from_image(
+            &star,
+            Vector::new(178.0, 0.0, 60.0),
+            Vector::new(200.0, 30.0, 200.0),
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.75, 0.6, 0.2)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 500.0, -250.0),
+                look_at: Vector::new(278.0, 0.0, 300.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// Flat blades of grass growing from the floor around a ball covered in
+/// cylindrical hair strands that droop under their own weight
+#[allow(dead_code)]
+pub fn config_cornell_box_grass() -> Config {
+    let size = 555.0;
+    let mut rng = thread_rng();
+    let mut objects = cornell_box(size);
+
+    // Blades of grass growing from the floor, each bending over in a
+    // random direction
+    for _ in 0..2000 {
+        let root = Vector::new(
+            rng.gen_range(20.0, size - 20.0),
+            0.0,
+            rng.gen_range(20.0, size - 20.0),
+        );
+        let height = rng.gen_range(60.0, 160.0);
+        let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+        let lean = Vector::new(angle.cos(), 0.0, angle.sin())
+            * rng.gen_range(0.1, 0.6)
+            * height;
+
+        let blade = Curve::new(
+            [
+                root,
+                root + Vector::new(0.0, height / 3.0, 0.0),
+                root + Vector::new(0.0, height * 2.0 / 3.0, 0.0) + lean / 3.0,
+                root + Vector::new(0.0, height, 0.0) + lean,
+            ],
+            (4.0, 0.5),
+            CurveType::Flat,
+        );
+
+        for segment in blade.split(2) {
+            objects.push(Object {
+                primitive: Primitive::Curve(segment),
+                transformation: None,
+                material: Material::Hair {
+                    color: Vector::new(0.2, 0.55, 0.1),
+                    roughness: 0.3,
+                },
+            });
+        }
+    }
+
+    let center = Vector::new(278.0, 150.0, 300.0);
+    let radius = 70.0;
+
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(center, radius)),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.3, 0.15, 0.05)),
+    });
+
+    for _ in 0..3000 {
+        let dir = loop {
+            let v = Vector::new(
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+                rng.gen_range(-1.0, 1.0),
+            );
+            if v.length_squared() <= 1.0 && v.length_squared() > 0.01 {
+                break v.normalize();
+            }
+        };
+
+        let root = center + dir * radius;
+        let length = rng.gen_range(30.0, 45.0);
+        let droop = Vector::new(0.0, -0.4 * length, 0.0);
+
+        let strand = Curve::new(
+            [
+                root,
+                root + dir * length / 3.0,
+                root + dir * length * 2.0 / 3.0 + droop / 3.0,
+                root + dir * length + droop,
+            ],
+            (1.5, 0.3),
+            CurveType::Cylinder,
+        );
+
+        for segment in strand.split(3) {
+            objects.push(Object {
+                primitive: Primitive::Curve(segment),
+                transformation: None,
+                material: Material::Hair {
+                    color: Vector::new(0.75, 0.45, 0.2),
+                    roughness: 0.25,
+                },
+            });
+        }
+    }
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// The Stanford bunny carved out of jade
+#[allow(dead_code)]
+pub fn config_cornell_box_bunny() -> Config {
+    let size = 555.0;
+    let mut file = fs::File::open("test/bunny.obj")
+        .map(io::BufReader::new)
+        .unwrap();
+
+    let bunny = ObjLoader::new()
+        .parse(&mut file, 2000.0, Vector::new(345.0, -66.0, 280.0))
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    eprintln!("Loaded OBJ: {} faces", bunny.face_count());
+
+    let mut objects = cornell_box(size);
+    objects.push(Object {
+        primitive: Primitive::Mesh(bunny),
+        transformation: None,
+        material: Material::Subsurface {
+            albedo: Vector::new(0.3, 0.75, 0.45),
+            mean_free_path: Vector::new(8.0, 25.0, 15.0),
+            ior: 1.6,
+        },
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 500,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+#[allow(dead_code)]
+pub fn config_cornell_box_motion_blur() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    objects.push(Object {
+        primitive: Primitive::MovingSphere(MovingSphere::new(
+            Vector::new(150.0, 100.0, 250.0),
+            Vector::new(150.0, 220.0, 250.0),
+            0.0,
+            1.0,
+            80.0,
+        )),
+        transformation: None,
+        material: Material::Diffuse(Vector::new(0.2, 0.45, 0.75)),
+    });
+
+    // A block that slides sideways and then lifts off during the shutter
+    objects.push(Object {
+        primitive: Primitive::Block(Block::new(
+            Vector::new(300.0, 0.0, 300.0),
+            Vector::new(440.0, 140.0, 440.0),
+        )),
+        transformation: Some(Transformation::Motion(Motion::Keyframed(
+            Keyframes::new(
+                vec![
+                    (0.0, Vector::zeros()),
+                    (0.5, Vector::new(60.0, 0.0, 0.0)),
+                    (1.0, Vector::new(60.0, 120.0, 0.0)),
+                ],
+                Interpolation::Linear,
+            ),
+        ))),
+        material: Material::Diffuse(Vector::new(0.73, 0.73, 0.73)),
+    });
+
+    // A small ball rolling towards the camera while the shutter is open
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(
+            Vector::new(220.0, 40.0, 120.0),
+            40.0,
+        )),
+        transformation: Some(Transformation::Motion(Motion::Linear {
+            from: Vector::zeros(),
+            to: Vector::new(0.0, 0.0, -80.0),
+            time0: 0.0,
+            time1: 1.0,
+        })),
+        material: Material::Diffuse(Vector::new(0.75, 0.6, 0.2)),
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
                 look_from: Vector::new(278.0, 278.0, -772.0),
                 look_at: Vector::new(278.0, 278.0, 0.0),
                 vup: Vector::new(0.0, 1.0, 0.0),
@@ -374,8 +1693,242 @@ pub fn config_cornell_box() -> Config {
                 aspect_r: 1.0,
                 aperture: 0.0,
                 focus_dist: 1.0,
+                shutter: (0.0, 1.0),
+                projection: Projection::Perspective,
             }),
+        ),
+        animation: None,
+    }
+}
+
+#[allow(dead_code)]
+pub fn config_cornell_box_smoke() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    // Dark, mostly absorbing smoke
+    objects.push(Object {
+        primitive: Primitive::Block(Block::new(
+            Vector::new(265.0, 0.0, 295.0),
+            Vector::new(430.0, 330.0, 460.0),
+        )),
+        transformation: None,
+        material: Material::Volume(Medium::Homogeneous {
+            sigma_a: Vector::new(0.008, 0.008, 0.008),
+            sigma_s: Vector::new(0.004, 0.004, 0.004),
+            g: 0.0,
+        }),
+    });
+
+    // A bright cloud that scatters blue light more than red
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(
+            Vector::new(170.0, 120.0, 170.0),
+            110.0,
+        )),
+        transformation: None,
+        material: Material::Volume(Medium::Homogeneous {
+            sigma_a: Vector::zeros(),
+            sigma_s: Vector::new(0.01, 0.02, 0.04),
+            g: 0.6,
+        }),
+    });
+
+    let mut scene = Scene::new(
+        objects,
+        Camera::new(CameraConstructor {
+            look_from: Vector::new(278.0, 278.0, -772.0),
+            look_at: Vector::new(278.0, 278.0, 0.0),
+            vup: Vector::new(0.0, 1.0, 0.0),
+            vfov: 40.0,
+            aspect_r: 1.0,
+            aperture: 0.0,
+            focus_dist: 1.0,
+            shutter: (0.0, 0.0),
+            projection: Projection::Perspective,
+        }),
+    );
+
+    // A light haze through the whole room
+    scene.fog = Some(Medium::Homogeneous {
+        sigma_a: Vector::zeros(),
+        sigma_s: Vector::new(0.0004, 0.0004, 0.0004),
+        g: 0.3,
+    });
+
+    Config {
+        resolution: (320, 320),
+        samples: 500,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene,
+        animation: None,
+    }
+}
+
+/// A cloud made from a few puffs high up in the box, above a puff of
+/// smoke loaded from a text grid and a ring of mist from raw 8-bit
+/// densities
+#[allow(dead_code)]
+pub fn config_cornell_box_cloud() -> Config {
+    let size = 555.0;
+    let mut objects = cornell_box(size);
+
+    // A few overlapping puffs with soft edges
+    let puffs = [
+        (Vector::new(0.5, 0.45, 0.5), 0.3),
+        (Vector::new(0.3, 0.4, 0.45), 0.22),
+        (Vector::new(0.7, 0.4, 0.55), 0.2),
+        (Vector::new(0.45, 0.65, 0.5), 0.2),
+    ];
+
+    let grid = VoxelGrid::from_fn(
+        (64, 48, 64),
+        Vector::new(80.0, 260.0, 150.0),
+        Vector::new(400.0, 200.0, 300.0),
+        |p| {
+            let d = puffs.iter().fold(0.0, |d: f32, (center, radius)| {
+                let falloff = 1.0 - (p - *center).length() / radius;
+                d.max(falloff)
+            });
+
+            // Ripples break up the surface of the puffs
+            let ripple =
+                (p.x * 40.0).sin() * (p.y * 37.0).sin() * (p.z * 43.0).sin();
+
+            (3.0 * d + 0.2 * ripple).clamp(0.0, 1.0)
         },
+    );
+
+    let puff = VoxelGrid::from_file(
+        "test/puff.vol",
+        Vector::new(40.0, 1.0, 180.0),
+        Vector::new(200.0, 230.0, 200.0),
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    let ring = VoxelGrid::from_raw(
+        "test/ring.raw",
+        (32, 32, 32),
+        Vector::new(300.0, 20.0, 180.0),
+        Vector::new(220.0, 220.0, 220.0),
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    let volumes = vec![
+        (grid, Vector::new(0.1, 0.1, 0.1), 0.7),
+        (puff, Vector::new(0.05, 0.06, 0.08), 0.0),
+        (ring, Vector::new(0.2, 0.2, 0.2), 0.6),
+    ];
+
+    for (grid, sigma_s, g) in volumes {
+        objects.push(Object {
+            primitive: Primitive::Block(grid.container()),
+            transformation: None,
+            material: Material::Volume(Medium::Grid {
+                grid: Arc::new(grid),
+                sigma_a: Vector::new(0.001, 0.001, 0.001),
+                sigma_s,
+                g,
+            }),
+        });
+    }
+
+    Config {
+        resolution: (320, 320),
+        samples: 500,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(
+            objects,
+            Camera::new(CameraConstructor {
+                look_from: Vector::new(278.0, 278.0, -772.0),
+                look_at: Vector::new(278.0, 278.0, 0.0),
+                vup: Vector::new(0.0, 1.0, 0.0),
+                vfov: 40.0,
+                aspect_r: 1.0,
+                aperture: 0.0,
+                focus_dist: 1.0,
+                shutter: (0.0, 0.0),
+                projection: Projection::Perspective,
+            }),
+        ),
+        animation: None,
+    }
+}
+
+/// Four seconds of the camera swinging around a box that rolls over the
+/// floor while a sphere changes color. Render with `nit --frames`.
+#[allow(dead_code)]
+pub fn config_cornell_box_animation() -> Config {
+    let size = 555.0;
+    let fps = 25.0;
+    let mut objects = cornell_box(size);
+
+    objects.push(Object {
+        primitive: Primitive::Block(Block::new(
+            Vector::new(60.0, 0.0, 300.0),
+            Vector::new(180.0, 120.0, 420.0),
+        )),
+        transformation: Some(Transformation::Motion(Motion::Keyframed(
+            Keyframes::new(
+                vec![
+                    (0.0, Vector::zeros()),
+                    (2.0, Vector::new(160.0, 0.0, -80.0)),
+                    (4.0, Vector::new(300.0, 0.0, 0.0)),
+                ],
+                Interpolation::Smooth,
+            ),
+        ))),
+        material: Material::Diffuse(Vector::new(0.73, 0.73, 0.73)),
+    });
+
+    objects.push(Object {
+        primitive: Primitive::Sphere(Sphere::new(
+            Vector::new(278.0, 90.0, 180.0),
+            90.0,
+        )),
+        transformation: None,
+        material: Material::Animated(Keyframes::new(
+            vec![
+                (0.0, Material::Diffuse(Vector::new(0.2, 0.45, 0.75))),
+                (2.0, Material::Diffuse(Vector::new(0.75, 0.6, 0.2))),
+                (4.0, Material::Diffuse(Vector::new(0.2, 0.45, 0.75))),
+            ],
+            Interpolation::Linear,
+        )),
+    });
+
+    let base = CameraConstructor {
+        look_from: Vector::new(278.0, 278.0, -772.0),
+        look_at: Vector::new(278.0, 278.0, 0.0),
+        vup: Vector::new(0.0, 1.0, 0.0),
+        vfov: 40.0,
+        aspect_r: 1.0,
+        aperture: 0.0,
+        focus_dist: 1.0,
+        // Open for half of each frame
+        shutter: (0.0, 0.5 / fps),
+        projection: Projection::Perspective,
+    };
+
+    let mut camera = CameraAnimation::still(base);
+    camera.look_from = Keyframes::new(
+        vec![
+            (0.0, Vector::new(100.0, 278.0, -772.0)),
+            (4.0, Vector::new(456.0, 278.0, -772.0)),
+        ],
+        Interpolation::Smooth,
+    );
+
+    Config {
+        resolution: (320, 320),
+        samples: 200,
+        tmo: ToneMappingOperator::ReinhardJodie,
+        scene: Scene::new(objects, camera.at(0.0)),
+        animation: Some(Animation {
+            fps,
+            frames: (0, 99),
+            camera,
+        }),
     }
 }
 
@@ -426,11 +1979,11 @@ fn cornell_box(size: f32) -> Vec<Object> {
                 RectPlane::XZ,
             )),
             transformation: None,
-            material: Material::Light(Vector::new(
+            material: Material::Light(Emitter::new(Vector::new(
                 25.2 / 2.0,
                 18.7 / 2.0,
                 6.0 / 2.0,
-            )),
+            ))),
         },
         // Ceiling
         Object {
@@ -476,3 +2029,43 @@ fn cornell_box(size: f32) -> Vec<Object> {
         },
     ]
 }
+
+/// A ground plane with a diffuse, a glass and a metal sphere on it
+#[allow(dead_code)]
+fn outdoor_spheres() -> Vec<Object> {
+    vec![
+        Object {
+            primitive: Primitive::Plane(Plane::new(
+                Vector::zeros(),
+                Vector::new(0.0, 1.0, 0.0),
+                50.0,
+            )),
+            transformation: None,
+            material: Material::Diffuse(Vector::new(0.5, 0.5, 0.5)),
+        },
+        Object {
+            primitive: Primitive::Sphere(Sphere::new(
+                Vector::new(-2.2, 1.0, 0.0),
+                1.0,
+            )),
+            transformation: None,
+            material: Material::Diffuse(Vector::new(0.8, 0.3, 0.2)),
+        },
+        Object {
+            primitive: Primitive::Sphere(Sphere::new(
+                Vector::new(0.0, 1.0, 0.0),
+                1.0,
+            )),
+            transformation: None,
+            material: Material::Dielectric(1.5),
+        },
+        Object {
+            primitive: Primitive::Sphere(Sphere::new(
+                Vector::new(2.2, 1.0, 0.0),
+                1.0,
+            )),
+            transformation: None,
+            material: Material::Reflector(Vector::new(0.8, 0.8, 0.8)),
+        },
+    ]
+}
@@ -16,12 +16,87 @@
 mod configs;
 pub use configs::*;
 
-use crate::{bvh::Bvh, camera::Camera, color::ToneMappingOperator};
+use crate::{
+    background::Background,
+    bvh::Bvh,
+    camera::{Camera, CameraAnimation},
+    color::ToneMappingOperator,
+    lights::Light,
+    media::Medium,
+    object::{Hit, Object},
+    ray::Ray,
+};
 
 #[derive(Debug)]
 pub struct Scene {
-    pub objects: Bvh,
+    /// Objects with finite bounds, if there are any
+    pub objects: Option<Bvh>,
+    /// Objects that extend forever, like planes, which are tested one by
+    /// one on every ray
+    pub unbounded: Vec<Object>,
     pub camera: Camera,
+    /// Lights with no size, which only light the scene through shadow
+    /// rays
+    pub lights: Vec<Light>,
+    /// A medium filling all of the space outside of objects
+    pub fog: Option<Medium>,
+    /// Light from far away, seen by rays that don't hit anything
+    pub background: Option<Background>,
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Object>, camera: Camera) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) =
+            objects.into_iter().partition(|o| o.primitive.is_bounded());
+
+        let mut scene = Self {
+            objects: if bounded.is_empty() {
+                None
+            } else {
+                Some(Bvh::new(bounded))
+            },
+            unbounded,
+            camera,
+            lights: Vec::new(),
+            fog: None,
+            background: None,
+        };
+
+        scene.autofocus();
+        scene
+    }
+
+    /// Focuses the camera on whatever is seen through its autofocus
+    /// point. Cameras without one, or that see nothing there, are left as
+    /// they are.
+    pub fn autofocus(&mut self) {
+        let p = self
+            .camera
+            .autofocus_ray()
+            .and_then(|r| self.hit(r, 0.0, f32::MAX))
+            .map(|hit| hit.intersection.p);
+
+        if let Some(p) = p {
+            self.camera.focus_on(p);
+        }
+    }
+
+    /// Finds the closest hit among all objects in the scene
+    pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let mut result = self
+            .objects
+            .as_ref()
+            .and_then(|objects| objects.hit(r, t_min, t_max));
+
+        for object in &self.unbounded {
+            let t_max = result.as_ref().map_or(t_max, |h| h.intersection.t);
+            if let Some(hit) = object.hit(r, t_min, t_max) {
+                result = Some(hit);
+            }
+        }
+
+        result
+    }
 }
 
 #[derive(Debug)]
@@ -30,4 +105,16 @@ pub struct Config {
     pub samples: usize,
     pub tmo: ToneMappingOperator,
     pub scene: Scene,
+    pub animation: Option<Animation>,
+}
+
+/// Describes how to render a scene as a sequence of frames. Objects and
+/// materials with keyframes are animated through the time of each ray,
+/// and the camera is moved to every frame before rendering it.
+#[derive(Debug)]
+pub struct Animation {
+    pub fps: f32,
+    /// The first and last frame rendered when no range is given
+    pub frames: (u32, u32),
+    pub camera: CameraAnimation,
 }
@@ -0,0 +1,208 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+/// A piecewise-constant distribution over [0, 1], used to pick samples
+/// in proportion to a tabulated function
+#[derive(Debug)]
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    /// The integral of `func` over [0, 1]
+    integral: f32,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f32>) -> Self {
+        assert!(!func.is_empty(), "distribution needs at least one value");
+
+        let n = func.len();
+        let mut cdf = Vec::with_capacity(n + 1);
+        cdf.push(0.0);
+        for i in 0..n {
+            cdf.push(cdf[i] + func[i].max(0.0) / n as f32);
+        }
+
+        let integral = cdf[n];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            // Fall back to a uniform distribution if everything is zero
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f32 / n as f32
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Maps a uniform random number to a sample in [0, 1). Returns the
+    /// sample, its pdf and the index of the segment it falls in.
+    pub fn sample(&self, u: f32) -> (f32, f32, usize) {
+        // The last entry of the cdf that is at or below u
+        let offset = self
+            .cdf
+            .partition_point(|&c| c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let (c0, c1) = (self.cdf[offset], self.cdf[offset + 1]);
+        let du = if c1 > c0 { (u - c0) / (c1 - c0) } else { 0.0 };
+
+        let x = (offset as f32 + du) / self.count() as f32;
+        (x.min(1.0 - f32::EPSILON), self.pdf_at(offset), offset)
+    }
+
+    /// The pdf of sampling a point in the given segment
+    pub fn pdf_at(&self, offset: usize) -> f32 {
+        if self.integral > 0.0 {
+            self.func[offset].max(0.0) / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+}
+
+/// A piecewise-constant distribution over [0, 1]^2, stored as a marginal
+/// distribution over rows and a conditional distribution within each row
+#[derive(Debug)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Builds the distribution from `func`, given row by row with `width`
+    /// values per row
+    pub fn new(func: &[f32], width: usize) -> Self {
+        let rows: Vec<Distribution1D> = func
+            .chunks_exact(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+
+        let marginal =
+            Distribution1D::new(rows.iter().map(|r| r.integral()).collect());
+
+        Self { rows, marginal }
+    }
+
+    /// Maps two uniform random numbers to a point `(u, v)`, where `v`
+    /// selects the row. Returns the point and its pdf.
+    pub fn sample(&self, u0: f32, u1: f32) -> ((f32, f32), f32) {
+        let (v, pdf_v, row) = self.marginal.sample(u1);
+        let (u, pdf_u, _) = self.rows[row].sample(u0);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// The pdf of sampling the point `(u, v)`
+    pub fn pdf(&self, u: f32, v: f32) -> f32 {
+        let clamp = |x: f32, n: usize| ((x * n as f32) as usize).min(n - 1);
+
+        let row = clamp(v, self.marginal.count());
+        let col = clamp(u, self.rows[row].count());
+
+        self.rows[row].pdf_at(col) * self.marginal.pdf_at(row)
+    }
+}
+
+/// Picks one of a set of items in proportion to their weights in constant
+/// time, using Vose's alias method
+#[derive(Debug)]
+pub struct AliasTable {
+    /// The chance of keeping each bin rather than taking its alias
+    probability: Vec<f32>,
+    alias: Vec<usize>,
+    /// The chance of picking each item overall
+    pmf: Vec<f32>,
+}
+
+impl AliasTable {
+    pub fn new(weights: &[f32]) -> Self {
+        assert!(!weights.is_empty(), "alias table needs at least one weight");
+
+        let n = weights.len();
+        let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+
+        // Fall back to picking uniformly if everything is zero
+        let pmf: Vec<f32> = weights
+            .iter()
+            .map(|w| {
+                if total > 0.0 {
+                    w.max(0.0) / total
+                } else {
+                    1.0 / n as f32
+                }
+            })
+            .collect();
+
+        let mut scaled: Vec<f32> = pmf.iter().map(|p| p * n as f32).collect();
+        let mut probability = vec![1.0; n];
+        let mut alias: Vec<usize> = (0..n).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..n).partition(|&i| scaled[i] < 1.0);
+
+        while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+            probability[s] = scaled[s];
+            alias[s] = l;
+
+            // The large bin gives away what the small one was missing
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        // Whatever is left over is full, give or take rounding error
+        Self {
+            probability,
+            alias,
+            pmf,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.pmf.len()
+    }
+
+    /// Maps a uniform random number to an item. Returns the index of the
+    /// item and the chance of picking it.
+    pub fn sample(&self, u: f32) -> (usize, f32) {
+        let x = u * self.count() as f32;
+        let bin = (x as usize).min(self.count() - 1);
+        let keep = x - bin as f32;
+
+        let index = if keep < self.probability[bin] {
+            bin
+        } else {
+            self.alias[bin]
+        };
+
+        (index, self.pmf[index])
+    }
+}
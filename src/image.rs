@@ -31,7 +31,7 @@ pub struct Pixel {
 }
 
 impl Pixel {
-    const LUMINANCE_TRIPLE: Vector = Vector::new(0.2126, 0.7152, 0.0722);
+    pub const LUMINANCE_TRIPLE: Vector = Vector::new(0.2126, 0.7152, 0.0722);
 
     pub fn luminance(&self) -> f32 {
         Vector::new(self.r, self.b, self.g).dot(Self::LUMINANCE_TRIPLE)
@@ -105,3 +105,519 @@ impl ImageBuffer {
             })
     }
 }
+
+/// An image loaded from disk. Channels of low dynamic range images are
+/// scaled to [0, 1], while HDR images keep their values as stored. Pixels
+/// are stored row by row starting from the top left corner.
+#[derive(Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Vector>,
+}
+
+impl Image {
+    /// Loads an image in any of the formats below, picked by the file
+    /// extension
+    pub fn from_file(in_path: &str) -> Result<Self, String> {
+        let extension = Path::new(in_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("ppm") | Some("pgm") => Self::from_ppm(in_path),
+            Some("pfm") => Self::from_pfm(in_path),
+            Some("hdr") => Self::from_hdr(in_path),
+            _ => {
+                Err(format!("Couldn't load {}: unknown image format", in_path))
+            }
+        }
+    }
+
+    /// Loads a PPM or PGM image, in either its plain (P2, P3) or raw
+    /// (P5, P6) form
+    pub fn from_ppm(in_path: &str) -> Result<Self, String> {
+        Self::load(in_path, Self::parse_ppm)
+    }
+
+    /// Loads a Portable Float Map, in either color (PF) or grayscale (Pf)
+    pub fn from_pfm(in_path: &str) -> Result<Self, String> {
+        Self::load(in_path, Self::parse_pfm)
+    }
+
+    /// Loads a Radiance HDR (RGBE) image, with or without run length
+    /// encoding
+    pub fn from_hdr(in_path: &str) -> Result<Self, String> {
+        Self::load(in_path, Self::parse_hdr)
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vector {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn luminance(&self, x: usize, y: usize) -> f32 {
+        self.pixel(x, y).dot(Pixel::LUMINANCE_TRIPLE)
+    }
+
+    /// The pixel under the texture coordinates `(u, v)`, where `v` runs
+    /// from the bottom of the image to the top
+    pub fn at_uv(&self, u: f32, v: f32) -> Vector {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y =
+            (((1.0 - v) * self.height as f32) as usize).min(self.height - 1);
+
+        self.pixel(x, y)
+    }
+
+    /// The average value of all of the pixels
+    pub fn average(&self) -> Vector {
+        self.pixels.iter().fold(Vector::zeros(), |acc, &p| acc + p)
+            / self.pixels.len() as f32
+    }
+
+    fn load<F>(in_path: &str, parse: F) -> Result<Self, String>
+    where
+        F: Fn(&[u8]) -> Result<Self, String>,
+    {
+        let path = Path::new(in_path);
+        let display = path.display();
+
+        let mut data = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|why| format!("Couldn't read {}: {}", display, why))?;
+
+        parse(&data)
+            .map_err(|why| format!("Couldn't load {}: {}", display, why))
+    }
+
+    fn parse_pfm(data: &[u8]) -> Result<Self, String> {
+        // The header is three lines: the format, the size and a scale
+        // whose sign gives the byte order
+        let mut lines = data.splitn(4, |&c| c == b'\n');
+        let mut header = || {
+            lines
+                .next()
+                .map(|l| String::from_utf8_lossy(l).trim().to_string())
+                .ok_or_else(|| String::from("truncated header"))
+        };
+
+        let channels = match header()?.as_str() {
+            "PF" => 3,
+            "Pf" => 1,
+            magic => return Err(format!("unsupported format {}", magic)),
+        };
+
+        let size = header()?;
+        let mut size = size.split_whitespace().map(|v| v.parse::<usize>());
+        let (width, height) = match (size.next(), size.next()) {
+            (Some(Ok(w)), Some(Ok(h))) if w > 0 && h > 0 => (w, h),
+            _ => return Err(String::from("invalid size")),
+        };
+
+        let scale = header()?
+            .parse::<f32>()
+            .map_err(|_| String::from("invalid scale"))?;
+
+        let raw = lines.next().unwrap_or(&[]);
+        let count = width * height * channels;
+        if raw.len() < count * 4 {
+            return Err(format!(
+                "expected {} values but found {}",
+                count,
+                raw.len() / 4
+            ));
+        }
+
+        let values: Vec<f32> = raw
+            .chunks_exact(4)
+            .take(count)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                if scale < 0.0 {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                }
+            })
+            .collect();
+
+        // Rows are stored from the bottom up
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in values.chunks_exact(width * channels).rev() {
+            pixels.extend(row.chunks_exact(channels).map(|c| {
+                if channels == 1 {
+                    Vector::new(c[0], c[0], c[0])
+                } else {
+                    Vector::new(c[0], c[1], c[2])
+                }
+            }));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn parse_hdr(data: &[u8]) -> Result<Self, String> {
+        if !data.starts_with(b"#?") {
+            return Err(String::from("missing #? signature"));
+        }
+
+        // Header lines end with a blank line, followed by the resolution
+        let mut pos = 0;
+        let mut next_line = || {
+            let start = pos;
+            while matches!(data.get(pos), Some(&c) if c != b'\n') {
+                pos += 1;
+            }
+            pos += 1;
+            String::from_utf8_lossy(&data[start..(pos - 1).min(data.len())])
+                .to_string()
+        };
+
+        loop {
+            let line = next_line();
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(format!("unsupported {}", line));
+            }
+        }
+
+        let resolution = next_line();
+        let tokens: Vec<&str> = resolution.split_whitespace().collect();
+        let (width, height) = match tokens.as_slice() {
+            ["-Y", h, "+X", w] => (w.parse::<usize>(), h.parse::<usize>()),
+            _ => return Err(format!("unsupported orientation {}", resolution)),
+        };
+        let (width, height) = match (width, height) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+            _ => return Err(String::from("invalid resolution")),
+        };
+
+        let truncated = || String::from("truncated pixel data");
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![[0_u8; 4]; width];
+
+        for _ in 0..height {
+            let rle = data.get(pos..pos + 4).map_or(false, |h| {
+                (8..0x8000).contains(&width)
+                    && h[0] == 2
+                    && h[1] == 2
+                    && (h[2] as usize) << 8 | h[3] as usize == width
+            });
+
+            if rle {
+                pos += 4;
+
+                // Each channel is stored in turn as runs and literals
+                for c in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = *data.get(pos).ok_or_else(truncated)?;
+                        pos += 1;
+
+                        if count > 128 {
+                            let count = (count - 128) as usize;
+                            let value = *data.get(pos).ok_or_else(truncated)?;
+                            pos += 1;
+                            for p in scanline.iter_mut().skip(x).take(count) {
+                                p[c] = value;
+                            }
+                            x += count;
+                        } else {
+                            let count = count as usize;
+                            let values = data
+                                .get(pos..pos + count)
+                                .ok_or_else(truncated)?;
+                            pos += count;
+                            for (p, &v) in
+                                scanline.iter_mut().skip(x).zip(values)
+                            {
+                                p[c] = v;
+                            }
+                            x += count;
+                        }
+                    }
+                }
+            } else {
+                for p in scanline.iter_mut() {
+                    let rgbe = data.get(pos..pos + 4).ok_or_else(truncated)?;
+                    p.copy_from_slice(rgbe);
+                    pos += 4;
+                }
+            }
+
+            pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+                if e == 0 {
+                    Vector::zeros()
+                } else {
+                    let f = 2_f32.powi(e as i32 - 136);
+                    Vector::new(r as f32, g as f32, b as f32) * f
+                }
+            }));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn parse_ppm(data: &[u8]) -> Result<Self, String> {
+        let mut pos = 0;
+        let mut header = [0; 4];
+        let mut plain = false;
+
+        // Magic number, width, height and maximum value, each followed
+        // by whitespace that may contain comments
+        for (i, field) in header.iter_mut().enumerate() {
+            loop {
+                match data.get(pos) {
+                    Some(b'#') => {
+                        while matches!(data.get(pos), Some(&c) if c != b'\n') {
+                            pos += 1;
+                        }
+                    }
+                    Some(c) if c.is_ascii_whitespace() => pos += 1,
+                    _ => break,
+                }
+            }
+
+            let start = pos;
+            while matches!(data.get(pos), Some(c) if !c.is_ascii_whitespace()) {
+                pos += 1;
+            }
+
+            let token = String::from_utf8_lossy(&data[start..pos]);
+            *field = if i == 0 {
+                // P2 and P3 store their values as text
+                plain = token == "P2" || token == "P3";
+                match token.as_ref() {
+                    "P2" | "P5" => 1,
+                    "P3" | "P6" => 3,
+                    _ => return Err(format!("unsupported format {}", token)),
+                }
+            } else {
+                token
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid header value {}", token))?
+            };
+        }
+
+        let [channels, width, height, max] = header;
+        if width == 0 || height == 0 || max == 0 || max > u16::MAX as usize {
+            return Err(String::from("invalid header"));
+        }
+
+        let count = width * height * channels;
+        let values: Vec<usize> = if plain {
+            String::from_utf8_lossy(&data[pos..])
+                .lines()
+                .map(|line| line.split('#').next().unwrap_or(""))
+                .flat_map(|line| line.split_whitespace())
+                .take(count)
+                .map(|v| {
+                    v.parse::<usize>()
+                        .map_err(|_| format!("invalid pixel value {}", v))
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            // A single whitespace character separates the header from
+            // the raw data
+            let raw = data.get(pos + 1..).unwrap_or(&[]);
+            if max < 256 {
+                raw.iter().take(count).map(|&v| v as usize).collect()
+            } else {
+                raw.chunks_exact(2)
+                    .take(count)
+                    .map(|v| (v[0] as usize) << 8 | v[1] as usize)
+                    .collect()
+            }
+        };
+
+        if values.len() < count {
+            return Err(format!(
+                "expected {} values but found {}",
+                count,
+                values.len()
+            ));
+        }
+
+        let scale = 1.0 / max as f32;
+        let pixels = values
+            .chunks_exact(channels)
+            .map(|c| {
+                let c: Vec<f32> = c.iter().map(|&v| v as f32 * scale).collect();
+                if channels == 1 {
+                    Vector::new(c[0], c[0], c[0])
+                } else {
+                    Vector::new(c[0], c[1], c[2])
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn assert_close(a: Vector, b: Vector) {
+        assert!((a - b).length() < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn parses_plain_and_raw_ppm() {
+        // A red and a blue pixel above a white and a black one
+        let plain =
+            b"P3\n# comment\n2 2\n255\n255 0 0  0 0 255\n255 255 255  0 0 0\n";
+        let mut raw = b"P6 2 2 255\n".to_vec();
+        raw.extend_from_slice(&[255, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0]);
+
+        for data in &[&plain[..], &raw[..]] {
+            let image = Image::parse_ppm(data).unwrap();
+            assert_eq!((image.width, image.height), (2, 2));
+            assert_close(image.pixel(0, 0), Vector::new(1.0, 0.0, 0.0));
+            assert_close(image.pixel(1, 0), Vector::new(0.0, 0.0, 1.0));
+            assert_close(image.pixel(0, 1), Vector::new(1.0, 1.0, 1.0));
+            assert_close(image.pixel(1, 1), Vector::zeros());
+        }
+    }
+
+    #[test]
+    fn parses_grayscale_ppm() {
+        let image = Image::parse_ppm(b"P2 2 1 4 1 3").unwrap();
+        assert_close(image.pixel(0, 0), Vector::new(0.25, 0.25, 0.25));
+        assert_close(image.pixel(1, 0), Vector::new(0.75, 0.75, 0.75));
+
+        // The format is read from the magic number, wherever the
+        // comments in front of it leave it
+        let image = Image::parse_ppm(b"# made by hand\nP2 2 1 4 1 3").unwrap();
+        assert_close(image.pixel(0, 0), Vector::new(0.25, 0.25, 0.25));
+
+        let image = Image::parse_ppm(b"P5 2 1 255\n3 ").unwrap();
+        let (a, b) = (b'3' as f32 / 255.0, b' ' as f32 / 255.0);
+        assert_close(image.pixel(0, 0), Vector::new(a, a, a));
+        assert_close(image.pixel(1, 0), Vector::new(b, b, b));
+    }
+
+    #[test]
+    fn parses_16_bit_ppm() {
+        // Raw values above 255 take two big-endian bytes
+        let mut data = b"P5 2 1 65535\n".to_vec();
+        data.extend_from_slice(&[0xff, 0xff, 0x80, 0x00]);
+
+        let image = Image::parse_ppm(&data).unwrap();
+        assert_close(image.pixel(0, 0), Vector::new(1.0, 1.0, 1.0));
+        let half = 32768.0 / 65535.0;
+        assert_close(image.pixel(1, 0), Vector::new(half, half, half));
+
+        let image = Image::parse_ppm(b"P2 1 1 1000 250").unwrap();
+        assert_close(image.pixel(0, 0), Vector::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn rejects_short_ppm() {
+        assert!(Image::parse_ppm(b"P6 2 2 255\n\0\0\0").is_err());
+        assert!(Image::parse_ppm(b"P4 2 2\n").is_err());
+    }
+
+    fn pfm(header: &str, values: &[f32], little_endian: bool) -> Vec<u8> {
+        let mut data = header.as_bytes().to_vec();
+        for v in values {
+            if little_endian {
+                data.extend_from_slice(&v.to_le_bytes());
+            } else {
+                data.extend_from_slice(&v.to_be_bytes());
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn parses_pfm_bottom_up_in_either_byte_order() {
+        // The bottom row comes first in the file
+        let values = [
+            0.0, 0.0, 0.0, 0.5, 0.5, 0.5, // bottom
+            1.0, 0.0, 0.0, 0.0, 2.0, 0.0, // top
+        ];
+
+        for &(scale, little_endian) in &[("-1.0", true), ("1.0", false)] {
+            let header = format!("PF\n2 2\n{}\n", scale);
+            let data = pfm(&header, &values, little_endian);
+
+            let image = Image::parse_pfm(&data).unwrap();
+            assert_eq!((image.width, image.height), (2, 2));
+            assert_close(image.pixel(0, 0), Vector::new(1.0, 0.0, 0.0));
+            assert_close(image.pixel(1, 0), Vector::new(0.0, 2.0, 0.0));
+            assert_close(image.pixel(0, 1), Vector::zeros());
+            assert_close(image.pixel(1, 1), Vector::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn parses_grayscale_pfm() {
+        let data = pfm("Pf\n1 2\n-1.0\n", &[0.25, 4.0], true);
+
+        let image = Image::parse_pfm(&data).unwrap();
+        assert_close(image.pixel(0, 0), Vector::new(4.0, 4.0, 4.0));
+        assert_close(image.pixel(0, 1), Vector::new(0.25, 0.25, 0.25));
+
+        let short = pfm("Pf\n2 2\n-1.0\n", &[1.0, 2.0, 3.0], true);
+        assert!(Image::parse_pfm(&short).is_err());
+    }
+
+    #[test]
+    fn parses_flat_hdr_scanlines() {
+        // Scanlines narrower than 8 pixels are never run-length encoded
+        let mut data =
+            b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let image = Image::parse_hdr(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_close(image.pixel(0, 0), Vector::new(1.0, 0.5, 0.0));
+        assert_close(image.pixel(1, 0), Vector::zeros());
+    }
+
+    #[test]
+    fn parses_rle_hdr_scanlines() {
+        let mut data =
+            b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // Red is a single run, green eight literals, blue a run followed
+        // by literals and the exponent another run
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[128 + 4, 64, 4, 0, 32, 64, 96]);
+        data.extend_from_slice(&[128 + 8, 129]);
+
+        let image = Image::parse_hdr(&data).unwrap();
+        assert_eq!((image.width, image.height), (8, 1));
+
+        for x in 0..8 {
+            let blue = if x < 4 { 0.5 } else { (x - 4) as f32 * 0.25 };
+            let expected = Vector::new(1.0, x as f32 * 0.125, blue);
+            assert_close(image.pixel(x, 0), expected);
+        }
+
+        // Dropping the exponent run leaves the scanline incomplete
+        data.truncate(data.len() - 2);
+        assert!(Image::parse_hdr(&data).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::Vector;
+
+use std::cmp::Ordering;
+
+/// Values that can be blended between keyframes
+pub trait Lerp: Clone {
+    /// Blends from `self` at `t = 0` to `other` at `t = 1`
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + t * (other - self)
+    }
+}
+
+impl Lerp for Vector {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        *self + t * (*other - *self)
+    }
+}
+
+/// How values change between two keyframes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    /// Holds each value until the next keyframe
+    Step,
+    /// Blends at a constant rate
+    Linear,
+    /// Blends with an ease-in and ease-out at every keyframe
+    Smooth,
+}
+
+/// A value that changes over time, given by its value at a number of
+/// points in time. The value is held before the first and after the last
+/// keyframe.
+#[derive(Debug, Clone)]
+pub struct Keyframes<T> {
+    keys: Vec<(f32, T)>,
+    interpolation: Interpolation,
+}
+
+impl<T: Lerp> Keyframes<T> {
+    /// Creates keyframes from (time, value) pairs, which may be given in
+    /// any order
+    pub fn new(mut keys: Vec<(f32, T)>, interpolation: Interpolation) -> Self {
+        assert!(!keys.is_empty(), "keyframes need at least one key");
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        Self {
+            keys,
+            interpolation,
+        }
+    }
+
+    /// A value that never changes
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0.0, value)], Interpolation::Step)
+    }
+
+    pub fn at(&self, time: f32) -> T {
+        let next = self.keys.iter().position(|k| k.0 > time);
+        let (t0, v0, t1, v1) = match next {
+            Some(0) => return self.keys[0].1.clone(),
+            None => return self.keys[self.keys.len() - 1].1.clone(),
+            Some(i) => {
+                let (t0, v0) = &self.keys[i - 1];
+                let (t1, v1) = &self.keys[i];
+                (*t0, v0, *t1, v1)
+            }
+        };
+
+        let s = (time - t0) / (t1 - t0);
+        match self.interpolation {
+            Interpolation::Step => v0.clone(),
+            Interpolation::Linear => v0.lerp(v1, s),
+            Interpolation::Smooth => v0.lerp(v1, s * s * (3.0 - 2.0 * s)),
+        }
+    }
+
+    /// The values at each keyframe. Every interpolation stays on the
+    /// straight line between neighbouring keys, so for positions these
+    /// bound the whole path.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.keys.iter().map(|k| &k.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    /// Rises from 0 to 10 over the first two seconds and falls back to 4
+    /// over the next two, given out of order
+    fn keys(interpolation: Interpolation) -> Keyframes<f32> {
+        Keyframes::new(vec![(4.0, 4.0), (0.0, 0.0), (2.0, 10.0)], interpolation)
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn interpolates_linearly() {
+        let k = keys(Interpolation::Linear);
+
+        assert_close(k.at(0.0), 0.0);
+        assert_close(k.at(0.5), 2.5);
+        assert_close(k.at(2.0), 10.0);
+        assert_close(k.at(3.0), 7.0);
+        assert_close(k.at(4.0), 4.0);
+    }
+
+    #[test]
+    fn steps_to_each_key() {
+        let k = keys(Interpolation::Step);
+
+        assert_close(k.at(1.99), 0.0);
+        assert_close(k.at(2.0), 10.0);
+        assert_close(k.at(3.99), 10.0);
+        assert_close(k.at(4.0), 4.0);
+    }
+
+    #[test]
+    fn eases_in_and_out() {
+        let k = keys(Interpolation::Smooth);
+
+        // A quarter of the way along is 0.25² (3 - 0.5) of the change,
+        // and the halfway point is unchanged
+        assert_close(k.at(0.5), 1.5625);
+        assert_close(k.at(1.0), 5.0);
+        assert_close(k.at(1.5), 10.0 - 1.5625);
+        assert_close(k.at(3.0), 7.0);
+    }
+
+    #[test]
+    fn holds_values_outside_the_keys() {
+        for &interpolation in &[
+            Interpolation::Step,
+            Interpolation::Linear,
+            Interpolation::Smooth,
+        ] {
+            let k = keys(interpolation);
+            assert_close(k.at(-1.0), 0.0);
+            assert_close(k.at(f32::MIN), 0.0);
+            assert_close(k.at(5.0), 4.0);
+            assert_close(k.at(f32::MAX), 4.0);
+        }
+
+        let k = Keyframes::constant(3.0);
+        assert_close(k.at(-10.0), 3.0);
+        assert_close(k.at(10.0), 3.0);
+    }
+
+    #[test]
+    fn accepts_nan_times() {
+        let k = Keyframes::new(
+            vec![(1.0, 1.0), (f32::NAN, 5.0), (0.0, 0.0)],
+            Interpolation::Linear,
+        );
+        assert_eq!(k.values().count(), 3);
+    }
+}
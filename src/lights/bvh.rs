@@ -0,0 +1,330 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use rand::prelude::*;
+
+use std::{cmp::Ordering, f32::consts::PI};
+
+use crate::{aabb::Aabb, Vector};
+
+/// Where a group of lights is, how much light they give off and which
+/// way it goes, used to guess how much they light a point
+#[derive(Debug, Copy, Clone)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    /// The total power of the lights
+    pub power: f32,
+    /// The axis of the cone that the surface normals of the lights lie in
+    pub axis: Vector,
+    /// Cosine of the half angle of the cone of normals
+    pub cos_theta_o: f32,
+    /// Cosine of the angle past the normals that light still leaves at,
+    /// which is 90 degrees for surfaces
+    pub cos_theta_e: f32,
+    pub two_sided: bool,
+}
+
+impl LightBounds {
+    /// Bounds of a light that shines in every direction from `bounds`
+    pub fn omni(bounds: Aabb, power: f32) -> Self {
+        Self {
+            bounds,
+            power,
+            axis: Vector::new(0.0, 1.0, 0.0),
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        }
+    }
+
+    /// An estimate of how much light reaches `p` from the lights. Lights
+    /// that can't reach `p` at all always give zero.
+    pub fn importance(&self, p: Vector) -> f32 {
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        let center = (min + max) / 2.0;
+        let radius = (max - min).length() / 2.0;
+
+        // Points close to or inside the bounds are treated as being at
+        // the edge of them, so that no light gets an infinite share
+        let to_point = p - center;
+        let d2 = to_point.length_squared().max(radius).max(f32::EPSILON);
+        let dir = to_point / to_point.length().max(f32::EPSILON);
+
+        let mut cos_theta_w = self.axis.dot(dir);
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = safe_sqrt(1.0 - cos_theta_w * cos_theta_w);
+
+        // The cone of directions the bounds take up as seen from `p`
+        let cos_theta_b = if to_point.length_squared() <= radius * radius {
+            -1.0
+        } else {
+            safe_sqrt(1.0 - radius * radius / to_point.length_squared())
+        };
+        let sin_theta_b = safe_sqrt(1.0 - cos_theta_b * cos_theta_b);
+
+        // The smallest angle between a normal in the cone and a direction
+        // towards `p` from anywhere in the bounds
+        let sin_theta_o = safe_sqrt(1.0 - self.cos_theta_o * self.cos_theta_o);
+        let (cos_theta_x, sin_theta_x) = sub_clamped(
+            cos_theta_w,
+            sin_theta_w,
+            self.cos_theta_o,
+            sin_theta_o,
+        );
+        let (cos_theta, _) =
+            sub_clamped(cos_theta_x, sin_theta_x, cos_theta_b, sin_theta_b);
+
+        if cos_theta <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        self.power * cos_theta / d2
+    }
+
+    /// Bounds covering both `self` and `other`
+    pub fn union(&self, other: &Self) -> Self {
+        if self.power <= 0.0 {
+            return *other;
+        }
+        if other.power <= 0.0 {
+            return *self;
+        }
+
+        let (axis, cos_theta_o) = cone_union(
+            (self.axis, self.cos_theta_o),
+            (other.axis, other.cos_theta_o),
+        );
+
+        Self {
+            bounds: Aabb::surrounding_box(self.bounds, other.bounds),
+            power: self.power + other.power,
+            axis,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LightBvhNodeType {
+    Internal((Box<LightBvh>, Box<LightBvh>)),
+    /// The index of a light in the sampler
+    Leaf(usize),
+}
+
+/// A tree of lights grouped by where they are, which is walked down to
+/// pick lights in proportion to how much they light a point
+#[derive(Debug)]
+pub struct LightBvh {
+    node_type: LightBvhNodeType,
+    bounds: LightBounds,
+}
+
+impl LightBvh {
+    /// Builds the tree from lights and their indices, splitting at the
+    /// median along the longest axis. The path from the root to each
+    /// light is written to `trails`, one bit per level with 1 meaning
+    /// the right child.
+    pub fn new(
+        mut lights: Vec<(usize, LightBounds)>,
+        trails: &mut [u64],
+    ) -> Self {
+        Self::build(&mut lights, 0, 0, trails)
+    }
+
+    fn build(
+        lights: &mut [(usize, LightBounds)],
+        trail: u64,
+        depth: u32,
+        trails: &mut [u64],
+    ) -> Self {
+        match lights.len() {
+            0 => panic!("wrong light bvh length"),
+            1 => {
+                let (index, bounds) = lights[0];
+                trails[index] = trail;
+
+                Self {
+                    node_type: LightBvhNodeType::Leaf(index),
+                    bounds,
+                }
+            }
+            l => {
+                let centroid =
+                    |b: &LightBounds| (b.bounds.min + b.bounds.max) / 2.0;
+
+                let mut min = centroid(&lights[0].1);
+                let mut max = min;
+                for (_, b) in lights.iter() {
+                    let c = centroid(b);
+                    for a in 0..3 {
+                        min[a] = min[a].min(c[a]);
+                        max[a] = max[a].max(c[a]);
+                    }
+                }
+
+                let extent = max - min;
+                let axis = if extent.x >= extent.y && extent.x >= extent.z {
+                    0
+                } else if extent.y >= extent.z {
+                    1
+                } else {
+                    2
+                };
+
+                lights.select_nth_unstable_by(l / 2, |a, b| {
+                    centroid(&a.1)[axis]
+                        .partial_cmp(&centroid(&b.1)[axis])
+                        .unwrap_or(Ordering::Equal)
+                });
+
+                // Paths deeper than the trail can hold would be lost, but
+                // splitting at the median keeps the tree far shallower
+                let (l_slice, r_slice) = lights.split_at_mut(l / 2);
+                let left = Self::build(l_slice, trail, depth + 1, trails);
+                let right = Self::build(
+                    r_slice,
+                    trail | (1 << depth.min(63)),
+                    depth + 1,
+                    trails,
+                );
+
+                let bounds = left.bounds.union(&right.bounds);
+
+                Self {
+                    node_type: LightBvhNodeType::Internal((
+                        Box::new(left),
+                        Box::new(right),
+                    )),
+                    bounds,
+                }
+            }
+        }
+    }
+
+    pub fn power(&self) -> f32 {
+        self.bounds.power
+    }
+
+    /// Walks down the tree towards the lights that light `p` the most.
+    /// Returns the index of the light and the chance of picking it.
+    pub fn sample(&self, p: Vector) -> Option<(usize, f32)> {
+        let mut rng = rand::thread_rng();
+        let mut node = self;
+        let mut pmf = 1.0;
+
+        loop {
+            match &node.node_type {
+                LightBvhNodeType::Leaf(index) => return Some((*index, pmf)),
+                LightBvhNodeType::Internal((left, right)) => {
+                    let l = left.bounds.importance(p);
+                    let r = right.bounds.importance(p);
+                    if l + r <= 0.0 {
+                        return None;
+                    }
+
+                    let p_left = l / (l + r);
+                    if rng.gen::<f32>() < p_left {
+                        pmf *= p_left;
+                        node = left;
+                    } else {
+                        pmf *= 1.0 - p_left;
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The chance of `sample` picking the light at the end of `trail`
+    /// from `p`
+    pub fn pmf(&self, p: Vector, trail: u64) -> f32 {
+        let mut node = self;
+        let mut trail = trail;
+        let mut pmf = 1.0;
+
+        while let LightBvhNodeType::Internal((left, right)) = &node.node_type {
+            let l = left.bounds.importance(p);
+            let r = right.bounds.importance(p);
+            if l + r <= 0.0 {
+                return 0.0;
+            }
+
+            if trail & 1 == 0 {
+                pmf *= l / (l + r);
+                node = left;
+            } else {
+                pmf *= r / (l + r);
+                node = right;
+            }
+            trail >>= 1;
+        }
+
+        pmf
+    }
+}
+
+fn safe_sqrt(x: f32) -> f32 {
+    x.max(0.0).sqrt()
+}
+
+/// The cosine and sine of the difference between two angles, given by
+/// theirs, clamped so that the difference never goes below zero
+fn sub_clamped(cos_a: f32, sin_a: f32, cos_b: f32, sin_b: f32) -> (f32, f32) {
+    if cos_a > cos_b {
+        (1.0, 0.0)
+    } else {
+        (cos_a * cos_b + sin_a * sin_b, sin_a * cos_b - cos_a * sin_b)
+    }
+}
+
+/// The smallest cone holding two cones, each given by its axis and the
+/// cosine of its half angle
+fn cone_union(a: (Vector, f32), b: (Vector, f32)) -> (Vector, f32) {
+    let whole_sphere = (a.0, -1.0);
+
+    let theta_a = a.1.clamp(-1.0, 1.0).acos();
+    let theta_b = b.1.clamp(-1.0, 1.0).acos();
+    let theta_d = a.0.dot(b.0).clamp(-1.0, 1.0).acos();
+
+    // One cone may already hold the other
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return a;
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return b;
+    }
+
+    let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+    if theta_o >= PI {
+        return whole_sphere;
+    }
+
+    // Turn the axis of `a` towards `b` until the cone reaches both
+    let k = a.0.cross(b.0);
+    if k.length_squared() <= 0.0 {
+        return whole_sphere;
+    }
+    let k = k / k.length();
+    let theta_r = theta_o - theta_a;
+    let axis = a.0 * theta_r.cos()
+        + k.cross(a.0) * theta_r.sin()
+        + k * k.dot(a.0) * (1.0 - theta_r.cos());
+
+    (axis, theta_o.cos())
+}
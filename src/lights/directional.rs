@@ -0,0 +1,26 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::LightSample;
+use crate::Vector;
+
+pub fn sample(direction: Vector, irradiance: Vector) -> LightSample {
+    LightSample {
+        dir: -direction / direction.length(),
+        distance: f32::INFINITY,
+        radiance: irradiance,
+        pdf: None,
+    }
+}
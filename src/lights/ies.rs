@@ -0,0 +1,391 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::Vector;
+
+use std::{f32::consts::PI, fs, path::Path};
+
+/// A photometric profile in the IES LM-63 format, describing how bright a
+/// light fixture is in each direction. Only type C photometry is
+/// supported, which covers nearly all architectural fixtures. A vertical
+/// angle of 0 is along the axis of the fixture, usually straight down.
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    /// Vertical angles in ascending order, in degrees
+    vertical: Vec<f32>,
+    /// Horizontal angles in ascending order, in degrees
+    horizontal: Vec<f32>,
+    /// Candela values for each horizontal angle, across all of the
+    /// vertical angles
+    candela: Vec<Vec<f32>>,
+}
+
+impl IesProfile {
+    pub fn from_file(in_path: &str) -> Result<Self, String> {
+        let path = Path::new(in_path);
+        let display = path.display();
+
+        let text = fs::read_to_string(path)
+            .map_err(|why| format!("Couldn't read {}: {}", display, why))?;
+
+        Self::parse(&text)
+            .map_err(|why| format!("Couldn't load {}: {}", display, why))
+    }
+
+    /// Scales the profile so that the fixture gives off `lumens` in total
+    pub fn scaled_to_lumens(mut self, lumens: f32) -> Self {
+        let scale = lumens / self.lumens();
+        if scale.is_finite() {
+            for row in &mut self.candela {
+                for c in row.iter_mut() {
+                    *c *= scale;
+                }
+            }
+        }
+
+        self
+    }
+
+    /// The total light given off by the fixture, found by integrating the
+    /// profile over the sphere
+    pub fn lumens(&self) -> f32 {
+        let (n_theta, n_phi) = (180, 360);
+        let (d_theta, d_phi) = (PI / n_theta as f32, 2.0 * PI / n_phi as f32);
+
+        let mut total = 0.0;
+        for i in 0..n_theta {
+            let theta = (i as f32 + 0.5) * d_theta;
+            for j in 0..n_phi {
+                let phi = (j as f32 + 0.5) * d_phi;
+                total += self.candela_at(theta.to_degrees(), phi.to_degrees())
+                    * theta.sin();
+            }
+        }
+
+        total * d_theta * d_phi
+    }
+
+    /// The intensity in candela towards `dir`, for a fixture pointing
+    /// along `axis`. Horizontal angles are measured around the axis from
+    /// the side facing +x, or +z for fixtures lying along the x axis.
+    pub fn intensity(&self, axis: Vector, dir: Vector) -> f32 {
+        let axis = axis / axis.length();
+        let dir = dir / dir.length();
+
+        let reference = if axis.x.abs() > 0.9 {
+            Vector::new(0.0, 0.0, 1.0)
+        } else {
+            Vector::new(1.0, 0.0, 0.0)
+        };
+        let u = reference - axis * axis.dot(reference);
+        let u = u / u.length();
+        let w = axis.cross(u);
+
+        let vertical = dir.dot(axis).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = dir.dot(w).atan2(dir.dot(u)).to_degrees();
+
+        self.candela_at(vertical, horizontal)
+    }
+
+    /// Looks up the candela value at the given angles in degrees,
+    /// unfolding the symmetry of the profile and interpolating between
+    /// the measured angles
+    fn candela_at(&self, vertical: f32, horizontal: f32) -> f32 {
+        let last = *self.horizontal.last().unwrap();
+        let mut h = horizontal.rem_euclid(360.0);
+
+        if self.horizontal.len() == 1 {
+            // Rotationally symmetric
+            h = self.horizontal[0];
+        } else if last <= 90.0 {
+            // Symmetric in each quadrant
+            if h > 180.0 {
+                h = 360.0 - h;
+            }
+            if h > 90.0 {
+                h = 180.0 - h;
+            }
+        } else if last <= 180.0 && h > 180.0 {
+            // Symmetric about the 0-180 plane
+            h = 360.0 - h;
+        }
+
+        let v_first = self.vertical[0];
+        let v_last = *self.vertical.last().unwrap();
+        if vertical < v_first || vertical > v_last {
+            return 0.0;
+        }
+
+        let (h0, h1, th) = bracket(&self.horizontal, h);
+        let (v0, v1, tv) = bracket(&self.vertical, vertical);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let row = |i: usize| lerp(self.candela[i][v0], self.candela[i][v1], tv);
+
+        lerp(row(h0), row(h1), th)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+
+        // Keywords and comments come before the tilt line
+        loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    let tilt = line.trim_start()["TILT=".len()..].trim();
+                    if tilt != "NONE" && tilt != "INCLUDE" {
+                        return Err(String::from(
+                            "tilt data in separate files isn't supported",
+                        ));
+                    }
+
+                    // Included tilt data is skipped over
+                    return Self::parse_data(lines, tilt == "INCLUDE");
+                }
+                Some(_) => continue,
+                None => return Err(String::from("missing TILT line")),
+            }
+        }
+    }
+
+    fn parse_data<'a, I>(lines: I, has_tilt: bool) -> Result<Self, String>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut values = lines
+            .flat_map(|line| {
+                line.split(|c: char| c.is_whitespace() || c == ',')
+            })
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<f32>()
+                    .map_err(|_| format!("invalid number {}", v))
+            });
+
+        let mut next = || {
+            values
+                .next()
+                .unwrap_or_else(|| Err(String::from("unexpected end of file")))
+        };
+
+        if has_tilt {
+            // Lamp to luminaire geometry, then pairs of angles and
+            // multiplying factors
+            next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let n_vertical = next()? as usize;
+        let n_horizontal = next()? as usize;
+        let photometric_type = next()?;
+        let _units = next()?;
+        let (_width, _length, _height) = (next()?, next()?, next()?);
+        let ballast_factor = next()?;
+        let ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1.0 {
+            return Err(format!(
+                "photometric type {} isn't supported",
+                photometric_type
+            ));
+        }
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(String::from("no angles given"));
+        }
+
+        let vertical = (0..n_vertical)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+        let horizontal = (0..n_horizontal)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let scale = multiplier * ballast_factor * ballast_lamp_factor;
+        let candela = (0..n_horizontal)
+            .map(|_| {
+                (0..n_vertical)
+                    .map(|_| next().map(|c| c * scale))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ascending = |a: &[f32]| a.windows(2).all(|w| w[0] < w[1]);
+        if !ascending(&vertical) || !ascending(&horizontal) {
+            return Err(String::from("angles must be in ascending order"));
+        }
+
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela,
+        })
+    }
+}
+
+/// Finds the two entries of the sorted `angles` around `x`, and how far
+/// `x` is between them
+fn bracket(angles: &[f32], x: f32) -> (usize, usize, f32) {
+    let n = angles.len();
+    if n == 1 || x <= angles[0] {
+        return (0, 0, 0.0);
+    }
+    if x >= angles[n - 1] {
+        return (n - 1, n - 1, 0.0);
+    }
+
+    let i = angles.partition_point(|&a| a <= x) - 1;
+    let t = (x - angles[i]) / (angles[i + 1] - angles[i]);
+
+    (i, i + 1, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.001;
+
+    /// A profile with the same intensity at every vertical angle in
+    /// `0..=max_vertical` for each of the given horizontal angles
+    fn profile(max_vertical: f32, horizontal: &[(f32, f32)]) -> IesProfile {
+        let angles: Vec<String> =
+            horizontal.iter().map(|(h, _)| h.to_string()).collect();
+        let rows: Vec<String> = horizontal
+            .iter()
+            .map(|(_, c)| format!("{} {}", c, c))
+            .collect();
+
+        let text = format!(
+            "IESNA:LM-63-2002\nTILT=NONE\n1 -1 1 2 {} 1 2 0 0 0\n1 1 0\n0 {}\n{}\n{}\n",
+            horizontal.len(),
+            max_vertical,
+            angles.join(" "),
+            rows.join("\n")
+        );
+
+        IesProfile::parse(&text).unwrap()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn rotationally_symmetric_profiles_ignore_the_horizontal_angle() {
+        let p = profile(180.0, &[(0.0, 100.0)]);
+
+        for &h in &[0.0, 45.0, 135.0, 200.0, -60.0, 359.0] {
+            assert_close(p.candela_at(45.0, h), 100.0);
+        }
+    }
+
+    #[test]
+    fn unfolds_quadrant_symmetry() {
+        let p = profile(180.0, &[(0.0, 10.0), (90.0, 30.0)]);
+
+        assert_close(p.candela_at(45.0, 30.0), 50.0 / 3.0);
+        // Mirrored across the 90 and the 0-180 planes
+        for &h in &[150.0, 210.0, 330.0, -30.0] {
+            assert_close(p.candela_at(45.0, h), 50.0 / 3.0);
+        }
+        assert_close(p.candela_at(45.0, 180.0), 10.0);
+        assert_close(p.candela_at(45.0, 270.0), 30.0);
+    }
+
+    #[test]
+    fn unfolds_bilateral_symmetry() {
+        let p = profile(180.0, &[(0.0, 10.0), (90.0, 30.0), (180.0, 50.0)]);
+
+        assert_close(p.candela_at(45.0, 135.0), 40.0);
+        // Mirrored across the 0-180 plane only
+        assert_close(p.candela_at(45.0, 225.0), 40.0);
+        assert_close(p.candela_at(45.0, 270.0), 30.0);
+        assert_close(p.candela_at(45.0, 300.0), p.candela_at(45.0, 60.0));
+    }
+
+    #[test]
+    fn full_profiles_are_not_unfolded() {
+        let p = profile(
+            180.0,
+            &[
+                (0.0, 10.0),
+                (90.0, 20.0),
+                (180.0, 30.0),
+                (270.0, 40.0),
+                (360.0, 10.0),
+            ],
+        );
+
+        assert_close(p.candela_at(45.0, 270.0), 40.0);
+        assert_close(p.candela_at(45.0, 315.0), 25.0);
+        assert_close(p.candela_at(45.0, -90.0), 40.0);
+    }
+
+    #[test]
+    fn integrates_lumens_over_the_sphere() {
+        // A uniform point source gives off 4π lumens per candela, and one
+        // that only lights the lower hemisphere half of that
+        let sphere = profile(180.0, &[(0.0, 100.0)]);
+        let lumens = sphere.lumens();
+        assert!((lumens / (400.0 * PI) - 1.0).abs() < 0.001, "{}", lumens);
+
+        let hemisphere = profile(90.0, &[(0.0, 100.0)]);
+        let lumens = hemisphere.lumens();
+        assert!((lumens / (200.0 * PI) - 1.0).abs() < 0.01, "{}", lumens);
+
+        let scaled = sphere.scaled_to_lumens(1000.0);
+        assert!((scaled.lumens() - 1000.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn parses_the_downlight_profile() {
+        let p = IesProfile::from_file("test/downlight.ies").unwrap();
+
+        // One rotationally symmetric row of 19 angles in 5 degree steps
+        assert_eq!(p.vertical.len(), 19);
+        assert_eq!(p.horizontal, vec![0.0]);
+        assert_close(p.candela_at(0.0, 0.0), 550.0);
+        assert_close(p.candela_at(35.0, 0.0), 803.1);
+        assert_close(p.candela_at(37.5, 123.0), (803.1 + 768.5) / 2.0);
+        assert_close(p.candela_at(120.0, 0.0), 0.0);
+
+        // The header gives absolute photometry, so the total comes from
+        // the zonal lumen method over the same table: each 5 degree band
+        // gives off 2π (cos θ0 - cos θ1) times its mean intensity
+        let zonal: f32 = p.vertical[..]
+            .windows(2)
+            .zip(p.candela[0].windows(2))
+            .map(|(v, c)| {
+                let (t0, t1) = (v[0].to_radians(), v[1].to_radians());
+                2.0 * PI * (t0.cos() - t1.cos()) * (c[0] + c[1]) / 2.0
+            })
+            .sum();
+
+        let lumens = p.lumens();
+        assert!(
+            (lumens / zonal - 1.0).abs() < 0.01,
+            "{} != {}",
+            lumens,
+            zonal
+        );
+    }
+}
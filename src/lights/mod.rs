@@ -0,0 +1,154 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::{f32::consts::PI, sync::Arc};
+
+use crate::{image::Pixel, Vector};
+
+mod bvh;
+mod directional;
+mod ies;
+mod point;
+mod sampler;
+mod spot;
+
+pub use ies::IesProfile;
+pub use sampler::LightSampler;
+
+/// A light with no size, which can't be seen or hit by rays and only
+/// lights the scene through shadow rays. Light from geometry uses
+/// `Material::Light` instead.
+#[derive(Debug, Clone)]
+pub enum Light {
+    /// Shines equally in all directions from a point, with `intensity`
+    /// given per unit of solid angle. With a `profile`, the light is
+    /// shaped like the fixture pointing straight down and `intensity`
+    /// tints it, so that a white light of 1 gives the profile in candela.
+    Point {
+        position: Vector,
+        intensity: Vector,
+        profile: Option<Arc<IesProfile>>,
+    },
+    /// A point light that only shines in a cone of `angle` degrees around
+    /// `direction`. The light fades out between `falloff` degrees and the
+    /// edge of the cone. A `profile` points along `direction` and is cut
+    /// off by the cone as well.
+    Spot {
+        position: Vector,
+        direction: Vector,
+        intensity: Vector,
+        angle: f32,
+        falloff: f32,
+        profile: Option<Arc<IesProfile>>,
+    },
+    /// Parallel light travelling along `direction` from infinitely far
+    /// away, like the sun. `irradiance` is the light falling on a surface
+    /// facing it.
+    Directional {
+        direction: Vector,
+        irradiance: Vector,
+    },
+}
+
+/// The light arriving at a point from a light
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Normalized direction from the point towards the light
+    pub dir: Vector,
+    /// Distance to the light, which is infinite for directional lights
+    pub distance: f32,
+    pub radiance: Vector,
+    /// The solid angle pdf of the direction, for lights with a size.
+    /// Lights with no size are always sampled in the same direction.
+    pub pdf: Option<f32>,
+}
+
+impl Light {
+    /// The light arriving at `p`, ignoring anything in the way
+    pub fn sample(&self, p: Vector) -> LightSample {
+        match self {
+            Self::Point {
+                position,
+                intensity,
+                profile,
+            } => {
+                let down = Vector::new(0.0, -1.0, 0.0);
+                let intensity =
+                    profiled(*intensity, profile, down, p - *position);
+                point::sample(*position, intensity, p)
+            }
+            Self::Spot {
+                position,
+                direction,
+                intensity,
+                angle,
+                falloff,
+                profile,
+            } => {
+                let intensity =
+                    profiled(*intensity, profile, *direction, p - *position);
+                spot::sample(
+                    *position, *direction, intensity, *angle, *falloff, p,
+                )
+            }
+            Self::Directional {
+                direction,
+                irradiance,
+            } => directional::sample(*direction, *irradiance),
+        }
+    }
+
+    /// The luminous power of the light, used to pick brighter lights more
+    /// often. Directional lights are treated as lighting a disk the size
+    /// of the scene, which has the given radius.
+    pub fn power(&self, scene_radius: f32) -> f32 {
+        let luminance = |v: Vector| v.dot(Pixel::LUMINANCE_TRIPLE).max(0.0);
+        let lumens = |profile: &Option<Arc<IesProfile>>, otherwise: f32| {
+            profile.as_ref().map_or(otherwise, |p| p.lumens())
+        };
+
+        match self {
+            Self::Point {
+                intensity, profile, ..
+            } => luminance(*intensity) * lumens(profile, 4.0 * PI),
+            Self::Spot {
+                intensity,
+                angle,
+                profile,
+                ..
+            } => {
+                let cone = 2.0 * PI * (1.0 - angle.to_radians().cos());
+                luminance(*intensity) * lumens(profile, cone)
+            }
+            Self::Directional { irradiance, .. } => {
+                luminance(*irradiance) * PI * scene_radius * scene_radius
+            }
+        }
+    }
+}
+
+/// Shapes `intensity` by the profile of a fixture pointing along `axis`,
+/// for light leaving the fixture along `dir`
+fn profiled(
+    intensity: Vector,
+    profile: &Option<Arc<IesProfile>>,
+    axis: Vector,
+    dir: Vector,
+) -> Vector {
+    match profile {
+        Some(profile) => intensity * profile.intensity(axis, dir),
+        None => intensity,
+    }
+}
@@ -0,0 +1,281 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use rand::prelude::*;
+
+use std::collections::HashMap;
+
+use super::{
+    bvh::{LightBounds, LightBvh},
+    Light, LightSample,
+};
+use crate::{
+    aabb::Aabb, config::Scene, distribution::AliasTable, materials::Material,
+    object::Object, primitives::Intersection, ray::Ray, Vector,
+};
+
+/// Something in the scene that gives off light and can be sampled
+#[derive(Debug)]
+enum Source<'a> {
+    Light(&'a Light),
+    /// An object with a `Material::Light` and a shape that points can be
+    /// picked on
+    Surface(&'a Object),
+}
+
+/// Picks a light for each shadow ray. Lights with no size are included
+/// along with untransformed objects that have a `Material::Light` and a
+/// shape with an area, like rectangles, quads and polygons.
+///
+/// Lights with a position are kept in a [`LightBvh`], which picks them by
+/// how much they are likely to light the point being shaded, so that
+/// scenes with thousands of small lights still find the nearby ones.
+/// Directional lights are picked by their power instead.
+///
+/// [`LightBvh`]: bvh/struct.LightBvh.html
+#[derive(Debug)]
+pub struct LightSampler<'a> {
+    sources: Vec<Source<'a>>,
+    /// The index of each emissive object in `sources`, by its address
+    surfaces: HashMap<usize, usize>,
+    bvh: Option<LightBvh>,
+    /// The path from the root of `bvh` to each source
+    trails: Vec<u64>,
+    /// Indices of the directional lights in `sources`
+    infinite: Vec<usize>,
+    infinite_table: Option<AliasTable>,
+    /// The chance of picking a directional light over one in `bvh`
+    p_infinite: f32,
+}
+
+impl<'a> LightSampler<'a> {
+    pub fn new(scene: &'a Scene) -> Self {
+        let (objects, scene_radius) = match &scene.objects {
+            Some(bvh) => {
+                let bbox = bvh.bounding_box();
+                (bvh.objects(), (bbox.max - bbox.min).length() / 2.0)
+            }
+            None => (Vec::new(), 1.0),
+        };
+
+        let mut sources: Vec<Source> =
+            scene.lights.iter().map(Source::Light).collect();
+        for object in objects.into_iter().chain(&scene.unbounded) {
+            let can_sample = object.transformation.is_none()
+                && object.primitive.area().is_some();
+
+            if can_sample && matches!(object.material, Material::Light(_)) {
+                sources.push(Source::Surface(object));
+            }
+        }
+
+        let mut bounded = Vec::new();
+        let (mut infinite, mut infinite_power) = (Vec::new(), Vec::new());
+
+        for (index, source) in sources.iter().enumerate() {
+            let bounds = match source {
+                Source::Light(light) => {
+                    let power = light.power(scene_radius);
+                    match light_bounds(light, power) {
+                        Some(bounds) => bounds,
+                        None => {
+                            infinite.push(index);
+                            infinite_power.push(power);
+                            continue;
+                        }
+                    }
+                }
+                Source::Surface(object) => match surface_bounds(object) {
+                    Some(bounds) => bounds,
+                    None => continue,
+                },
+            };
+
+            // Lights that give off nothing are never picked
+            if bounds.power > 0.0 {
+                bounded.push((index, bounds));
+            }
+        }
+
+        let surfaces: HashMap<usize, usize> = bounded
+            .iter()
+            .filter_map(|&(index, _)| match sources[index] {
+                Source::Surface(object) => Some((address(object), index)),
+                Source::Light(_) => None,
+            })
+            .collect();
+
+        let mut trails = vec![0; sources.len()];
+        let bvh = if bounded.is_empty() {
+            None
+        } else {
+            Some(LightBvh::new(bounded, &mut trails))
+        };
+
+        let total_infinite: f32 = infinite_power.iter().sum();
+        let total = total_infinite + bvh.as_ref().map_or(0.0, |b| b.power());
+        let p_infinite = if total > 0.0 {
+            total_infinite / total
+        } else {
+            0.0
+        };
+
+        Self {
+            sources,
+            surfaces,
+            bvh,
+            trails,
+            infinite_table: if infinite.is_empty() {
+                None
+            } else {
+                Some(AliasTable::new(&infinite_power))
+            },
+            infinite,
+            p_infinite,
+        }
+    }
+
+    /// Picks a light and samples the light arriving from it at the origin
+    /// of `from`, ignoring anything in the way. Returns the sample and the
+    /// chance of picking the light.
+    pub fn sample(&self, from: Ray) -> Option<(LightSample, f32)> {
+        let (index, pmf) = if random::<f32>() < self.p_infinite {
+            let (i, pmf) = self.infinite_table.as_ref()?.sample(random());
+            (self.infinite[i], self.p_infinite * pmf)
+        } else {
+            let (i, pmf) = self.bvh.as_ref()?.sample(from.origin)?;
+            (i, (1.0 - self.p_infinite) * pmf)
+        };
+
+        if pmf <= 0.0 {
+            return None;
+        }
+
+        let sample = match self.sources[index] {
+            Source::Light(light) => light.sample(from.origin),
+            Source::Surface(object) => sample_surface(object, from)?,
+        };
+
+        Some((sample, pmf))
+    }
+
+    /// The solid angle pdf of `sample` picking the point `i` on `object`
+    /// from `origin`, or `None` if the object isn't sampled as a light
+    pub fn pdf(
+        &self,
+        object: &Object,
+        origin: Vector,
+        i: Intersection,
+    ) -> Option<f32> {
+        if !matches!(object.material, Material::Light(_)) {
+            return None;
+        }
+
+        let index = *self.surfaces.get(&address(object))?;
+        let pmf = (1.0 - self.p_infinite)
+            * self.bvh.as_ref()?.pmf(origin, self.trails[index]);
+        let area = object.primitive.area()?;
+
+        let to_light = i.p - origin;
+        let cos_theta = to_light.dot(i.normal).abs()
+            / (to_light.length() * i.normal.length());
+
+        if cos_theta <= 0.0 {
+            return Some(0.0);
+        }
+
+        Some(pmf * to_light.length_squared() / (cos_theta * area))
+    }
+}
+
+/// Bounds of a light with no size, or `None` for directional lights that
+/// are infinitely far away
+fn light_bounds(light: &Light, power: f32) -> Option<LightBounds> {
+    match light {
+        Light::Point { position, .. } => {
+            Some(LightBounds::omni(Aabb::new(*position, *position), power))
+        }
+        Light::Spot {
+            position,
+            direction,
+            angle,
+            falloff,
+            ..
+        } => {
+            let falloff = falloff.min(*angle);
+            Some(LightBounds {
+                bounds: Aabb::new(*position, *position),
+                power,
+                axis: *direction / direction.length(),
+                cos_theta_o: falloff.to_radians().cos(),
+                cos_theta_e: (angle - falloff).to_radians().cos(),
+                two_sided: false,
+            })
+        }
+        Light::Directional { .. } => None,
+    }
+}
+
+/// Bounds of an emissive object. The shapes that can be sampled are all
+/// flat, so any point on them gives the normal.
+fn surface_bounds(object: &Object) -> Option<LightBounds> {
+    let (emitter, area) = match (&object.material, object.primitive.area()) {
+        (Material::Light(emitter), Some(area)) => (emitter, area),
+        _ => return None,
+    };
+    let normal = object.primitive.sample()?.normal;
+
+    Some(LightBounds {
+        bounds: object.bounding_box(),
+        power: emitter.power_per_area() * area,
+        axis: normal / normal.length(),
+        cos_theta_o: 1.0,
+        cos_theta_e: 0.0,
+        two_sided: emitter.two_sided,
+    })
+}
+
+/// Objects are told apart by where they are in memory, which stays the
+/// same while the scene is borrowed
+fn address(object: &Object) -> usize {
+    object as *const Object as usize
+}
+
+/// Picks a point on an emissive object, and finds the light leaving it
+/// towards the origin of `from`
+fn sample_surface(object: &Object, from: Ray) -> Option<LightSample> {
+    let point = object.primitive.sample()?;
+
+    let to_light = point.p - from.origin;
+    let distance = to_light.length();
+    let dir = to_light / distance;
+
+    let cos_theta = dir.dot(point.normal).abs() / point.normal.length();
+    if distance <= 0.0 || cos_theta <= 0.0 {
+        return None;
+    }
+
+    // Hitting the point again gives its texture coordinates, and the
+    // light leaving whichever side of the surface faces `from`
+    let r = Ray { dir, ..from };
+    let hit = object.hit(r, distance * 0.999, distance * 1.001)?;
+
+    Some(LightSample {
+        dir,
+        distance: hit.intersection.t,
+        radiance: hit.emitted,
+        pdf: Some(point.pdf * distance * distance / cos_theta),
+    })
+}
@@ -0,0 +1,47 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{point, LightSample};
+use crate::Vector;
+
+/// Samples the spot light like a point light, scaled by how far inside
+/// the cone `p` is. The fade between `falloff` and `angle` is smoothed so
+/// that the edge of the cone doesn't show.
+pub fn sample(
+    position: Vector,
+    direction: Vector,
+    intensity: Vector,
+    angle: f32,
+    falloff: f32,
+    p: Vector,
+) -> LightSample {
+    let mut sample = point::sample(position, intensity, p);
+
+    let cos_theta = -sample.dir.dot(direction) / direction.length();
+    let cos_outer = angle.to_radians().cos();
+    let cos_inner = falloff.min(angle).to_radians().cos();
+
+    let scale = if cos_theta >= cos_inner {
+        1.0
+    } else if cos_theta <= cos_outer {
+        0.0
+    } else {
+        let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    };
+
+    sample.radiance *= scale;
+    sample
+}
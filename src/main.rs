@@ -3,13 +3,19 @@
 #![feature(generic_const_exprs)]
 
 mod aabb;
+mod background;
 mod bvh;
 mod camera;
 mod color;
 mod config;
+mod distribution;
 mod image;
+mod keyframes;
+mod lights;
 mod materials;
 mod math;
+mod media;
+mod obj_loader;
 mod object;
 mod primitives;
 mod ray;
@@ -35,16 +41,67 @@ mod vector3;
 use crate::config::*;
 use crate::{image::ImageBuffer, vector3::Vector};
 
-use std::time;
+use std::{env, time};
+
+const USAGE: &str = "usage: nit [--frames [FIRST LAST] | --fps]";
 
 fn main() -> Result<(), String> {
     let start_time = time::Instant::now();
+    let args: Vec<String> = env::args().skip(1).collect();
+
     // let config = config_stl_test();
-    let config = config_cornell_box();
+    let mut config = match args.first().map(String::as_str) {
+        Some("--frames") | Some("--fps") => config_cornell_box_animation(),
+        _ => config_cornell_box(),
+    };
     let tmo = config.tmo;
 
-    let mut buffer = ImageBuffer::new(config.resolution);
-    renderer::render(&mut buffer, config);
-    println!("\nCompleted rendering in {:#?}", start_time.elapsed());
-    buffer.to_ppm(String::from("out/image.ppm"), tmo)
+    match args.first().map(String::as_str) {
+        None => {
+            let mut buffer = ImageBuffer::new(config.resolution);
+            renderer::render(&mut buffer, &config);
+            println!("\nCompleted rendering in {:#?}", start_time.elapsed());
+            buffer.to_ppm(String::from("out/image.ppm"), tmo)
+        }
+        Some("--frames") => {
+            let animation = config
+                .animation
+                .take()
+                .ok_or("The scene has no animation to render")?;
+
+            let (first, last) = match &args[1..] {
+                [] => animation.frames,
+                [first, last] => (
+                    first.parse().map_err(|_| USAGE)?,
+                    last.parse().map_err(|_| USAGE)?,
+                ),
+                _ => return Err(String::from(USAGE)),
+            };
+
+            // The scene is only built once, and every frame just moves the
+            // camera to a later point in time
+            for frame in first..=last {
+                println!("Frame {} of {}", frame, last);
+                config.scene.camera =
+                    animation.camera.at(frame as f32 / animation.fps);
+                config.scene.autofocus();
+
+                let mut buffer = ImageBuffer::new(config.resolution);
+                renderer::render(&mut buffer, &config);
+                buffer.to_ppm(format!("out/frame_{:04}.ppm", frame), tmo)?;
+                println!();
+            }
+
+            println!("Completed rendering in {:#?}", start_time.elapsed());
+            Ok(())
+        }
+        Some("--fps") if args.len() == 1 => {
+            let animation = config
+                .animation
+                .ok_or("The scene has no animation to render")?;
+            println!("{}", animation.fps);
+            Ok(())
+        }
+        Some(_) => Err(String::from(USAGE)),
+    }
 }
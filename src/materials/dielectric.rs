@@ -35,7 +35,7 @@ pub struct Dielectric {
 /// during reflection calculations
 ///
 /// [More info](https://en.wikipedia.org/wiki/Schlick%27s_approximation)
-fn schlick(cosine: f32, ref_idx: f32) -> f32 {
+pub(super) fn schlick(cosine: f32, ref_idx: f32) -> f32 {
     let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
     let r0 = r0 * r0;
 
@@ -72,6 +72,7 @@ pub fn scatter(ref_idx: f32, r_in: Ray, i: Intersection) -> Option<Scatter> {
             specular: Ray {
                 origin: i.p,
                 dir: refracted.unwrap(),
+                time: r_in.time,
             },
             attenuation: Vector::ones(),
         })
@@ -80,6 +81,7 @@ pub fn scatter(ref_idx: f32, r_in: Ray, i: Intersection) -> Option<Scatter> {
             specular: Ray {
                 origin: i.p,
                 dir: reflected,
+                time: r_in.time,
             },
             attenuation: Vector::ones(),
         })
@@ -18,12 +18,15 @@ use crate::{
     ray::Ray, Vector,
 };
 
-pub fn scatter(albedo: Vector, _r: Ray, i: Intersection) -> Option<Scatter> {
+use std::f32;
+
+pub fn scatter(albedo: Vector, r: Ray, i: Intersection) -> Option<Scatter> {
     let scattered = i.p + i.normal + random_on_unit_sphere();
 
     let specular = Ray {
         origin: i.p,
         dir: (scattered - i.p),
+        time: r.time,
     };
 
     Some(Scatter {
@@ -31,3 +34,13 @@ pub fn scatter(albedo: Vector, _r: Ray, i: Intersection) -> Option<Scatter> {
         attenuation: albedo,
     })
 }
+
+/// The reflected light towards the viewer for light arriving from `dir`,
+/// including the cosine term, along with the pdf of `scatter` choosing
+/// `dir`
+pub fn bsdf(albedo: Vector, i: Intersection, dir: Vector) -> (Vector, f32) {
+    let cosine = (i.normal.dot(dir) / dir.length()).max(0.0);
+    let pdf = cosine * f32::consts::FRAC_1_PI;
+
+    (albedo * pdf, pdf)
+}
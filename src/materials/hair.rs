@@ -0,0 +1,77 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::{
+    materials::{dielectric::schlick, diffuse, Scatter},
+    math::Onb,
+    primitives::Intersection,
+    ray::Ray,
+    Vector,
+};
+
+use rand::prelude::*;
+use std::f32;
+
+/// Index of refraction of the hair fiber
+const HAIR_IOR: f32 = 1.55;
+
+/// A basic hair model after Kajiya and Kay. Light leaves the fiber on a
+/// cone around its tangent mirroring the incoming direction, where
+/// `roughness` (in radians) blurs the cone. Light reflected off the
+/// surface keeps its color, while light that travels through the fiber
+/// takes on the hair color and spreads out further.
+///
+/// Primitives without a tangent scatter like a diffuse surface.
+pub fn scatter(
+    color: Vector,
+    roughness: f32,
+    r: Ray,
+    i: Intersection,
+) -> Option<Scatter> {
+    let tangent = match i.tangent {
+        Some(tangent) => tangent,
+        None => return diffuse::scatter(color, r, i),
+    };
+
+    let mut rng = thread_rng();
+    let incoming = -r.dir / r.dir.length();
+    let theta_i = incoming.dot(tangent).clamp(-1.0, 1.0).asin();
+
+    let (attenuation, spread) =
+        if rng.gen::<f32>() < schlick(theta_i.cos(), HAIR_IOR) {
+            (Vector::ones(), roughness)
+        } else {
+            (color, 2.0 * roughness)
+        };
+
+    let theta_o = (-theta_i + spread * (2.0 * rng.gen::<f32>() - 1.0))
+        .clamp(-f32::consts::FRAC_PI_2, f32::consts::FRAC_PI_2);
+    let phi = 2.0 * f32::consts::PI * rng.gen::<f32>();
+
+    let dir = Onb::build_from_w(tangent).local(Vector::new(
+        theta_o.cos() * phi.cos(),
+        theta_o.cos() * phi.sin(),
+        theta_o.sin(),
+    ));
+
+    Some(Scatter {
+        specular: Ray {
+            origin: i.p,
+            dir,
+            time: r.time,
+        },
+        attenuation,
+    })
+}
@@ -13,12 +13,95 @@
  * You should have received a copy of the GNU General Public License
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
-use crate::{primitives::Intersection, ray::Ray, Vector};
+use std::sync::Arc;
 
-pub fn emitted(emittance: Vector, r: Ray, i: Intersection) -> Vector {
-    if i.normal.dot(r.dir) < 0.0 {
-        emittance
+use crate::{
+    image::{Image, Pixel},
+    keyframes::Lerp,
+    lights::IesProfile,
+    primitives::Intersection,
+    ray::Ray,
+    Vector,
+};
+
+/// Light given off by a surface
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub radiance: Vector,
+    /// Shapes the light like a fixture pointing out of the surface. Each
+    /// square unit of the surface then gives off the profile in candela,
+    /// tinted by `radiance`.
+    pub profile: Option<Arc<IesProfile>>,
+    /// Whether the back of the surface gives off light as well
+    pub two_sided: bool,
+    /// An image that the radiance is multiplied by, placed using the
+    /// texture coordinates of the surface
+    pub texture: Option<Arc<Image>>,
+}
+
+impl Emitter {
+    /// A one-sided emitter that gives off the same `radiance` everywhere
+    /// and in every direction
+    pub fn new(radiance: Vector) -> Self {
+        Self {
+            radiance,
+            profile: None,
+            two_sided: false,
+            texture: None,
+        }
+    }
+
+    /// The luminous power given off by each square unit of the surface,
+    /// on average
+    pub fn power_per_area(&self) -> f32 {
+        let mut radiance = self.radiance;
+        if let Some(texture) = &self.texture {
+            radiance *= texture.average();
+        }
+
+        let per_side = match &self.profile {
+            Some(profile) => profile.lumens(),
+            None => std::f32::consts::PI,
+        };
+        let sides = if self.two_sided { 2.0 } else { 1.0 };
+
+        radiance.dot(Pixel::LUMINANCE_TRIPLE).max(0.0) * per_side * sides
+    }
+}
+
+impl Lerp for Emitter {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            radiance: self.radiance.lerp(&other.radiance, t),
+            ..if t < 0.5 { self.clone() } else { other.clone() }
+        }
+    }
+}
+
+pub fn emitted(emitter: &Emitter, r: Ray, i: Intersection) -> Vector {
+    // The side of the surface facing the ray
+    let normal = if i.normal.dot(r.dir) < 0.0 {
+        i.normal
+    } else if emitter.two_sided {
+        -i.normal
     } else {
-        Vector::zeros()
+        return Vector::zeros();
+    };
+
+    let mut radiance = emitter.radiance;
+    if let Some(texture) = &emitter.texture {
+        radiance *= texture.at_uv(i.u, i.v);
+    }
+
+    match &emitter.profile {
+        Some(profile) => {
+            // Radiance is intensity per unit of projected area. The cosine
+            // is kept away from zero so grazing angles don't blow up.
+            let cos_theta = (-r.dir.dot(normal)
+                / (r.dir.length() * normal.length()))
+            .max(1e-2);
+            radiance * profile.intensity(normal, -r.dir) / cos_theta
+        }
+        None => radiance,
     }
 }
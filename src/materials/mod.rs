@@ -15,12 +15,23 @@
  */
 use std::fmt::Debug;
 
-use crate::{primitives::Intersection, ray::Ray, Vector};
+use crate::{
+    keyframes::{Keyframes, Lerp},
+    media::Medium,
+    primitives::Intersection,
+    ray::Ray,
+    Vector,
+};
 
 mod dielectric;
 mod diffuse;
+mod hair;
 mod light;
 mod reflector;
+mod subsurface;
+mod volume;
+
+pub use light::Emitter;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Scatter {
@@ -28,30 +39,149 @@ pub struct Scatter {
     pub attenuation: Vector,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Material {
+    /// A material whose values change over time, evaluated at the time
+    /// of each ray
+    Animated(Keyframes<Material>),
     Dielectric(f32),
     Diffuse(Vector),
-    Light(Vector),
+    /// A hair fiber with the given color and roughness, for curves
+    Hair {
+        color: Vector,
+        roughness: f32,
+    },
+    Light(Emitter),
     Reflector(Vector),
+    /// A translucent material like skin, wax or marble, where light
+    /// scatters around under the surface before leaving it. `albedo` is
+    /// the overall color, and `mean_free_path` is how far light travels
+    /// inside between scattering events for each color channel. The
+    /// object should be closed.
+    Subsurface {
+        albedo: Vector,
+        mean_free_path: Vector,
+        ior: f32,
+    },
+    /// An invisible boundary filled with a participating medium. The
+    /// object should be closed, like a `Sphere` or `Block`.
+    Volume(Medium),
 }
 
 impl Material {
     pub fn scatter(&self, r: Ray, i: Intersection) -> Option<Scatter> {
         match self {
+            Self::Animated(keys) => keys.at(r.time).scatter(r, i),
             Self::Dielectric(ref_idx) => dielectric::scatter(*ref_idx, r, i),
             Self::Diffuse(albedo) => diffuse::scatter(*albedo, r, i),
+            Self::Hair { color, roughness } => {
+                hair::scatter(*color, *roughness, r, i)
+            }
             Self::Light(_) => None,
             Self::Reflector(albedo) => reflector::scatter(*albedo, r, i),
+            Self::Subsurface { ior, .. } => subsurface::scatter(*ior, r, i),
+            Self::Volume(_) => volume::scatter(r, i),
         }
     }
 
     pub fn emitted(&self, r: Ray, i: Intersection) -> Vector {
         match self {
+            Self::Animated(keys) => keys.at(r.time).emitted(r, i),
             Self::Dielectric(_) => Vector::zeros(),
             Self::Diffuse(_) => Vector::zeros(),
-            Self::Light(emittance) => light::emitted(*emittance, r, i),
+            Self::Hair { .. } => Vector::zeros(),
+            Self::Light(emitter) => light::emitted(emitter, r, i),
             Self::Reflector(_) => Vector::zeros(),
+            Self::Subsurface { .. } => Vector::zeros(),
+            Self::Volume(_) => Vector::zeros(),
+        }
+    }
+
+    /// Evaluates the material for light arriving from `dir` and leaving
+    /// back along the ray `r`, so that light sources can be sampled
+    /// directly. Returns the BSDF times the cosine term, and the pdf of
+    /// `scatter` choosing `dir`. Materials that scatter in a single
+    /// direction, like glass and mirrors, can't be sampled this way and
+    /// return `None`.
+    pub fn bsdf(
+        &self,
+        r: Ray,
+        i: Intersection,
+        dir: Vector,
+    ) -> Option<(Vector, f32)> {
+        match self {
+            Self::Animated(keys) => keys.at(r.time).bsdf(r, i, dir),
+            Self::Diffuse(albedo) => Some(diffuse::bsdf(*albedo, i, dir)),
+            _ => None,
+        }
+    }
+
+    /// Whether the material only marks the edge of a medium and lets
+    /// light straight through
+    pub fn is_volume_boundary(&self, time: f32) -> bool {
+        match self {
+            Self::Animated(keys) => keys.at(time).is_volume_boundary(time),
+            Self::Volume(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The medium inside objects made of this material, if there is one
+    pub fn medium(&self, time: f32) -> Option<Medium> {
+        match self {
+            Self::Animated(keys) => keys.at(time).medium(time),
+            Self::Subsurface {
+                albedo,
+                mean_free_path,
+                ..
+            } => Some(subsurface::medium(*albedo, *mean_free_path)),
+            Self::Volume(medium) => Some(medium.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Blends the values of two materials of the same kind. Materials of
+/// different kinds switch over halfway.
+impl Lerp for Material {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        match (self, other) {
+            (Self::Dielectric(a), Self::Dielectric(b)) => {
+                Self::Dielectric(a.lerp(b, t))
+            }
+            (Self::Diffuse(a), Self::Diffuse(b)) => Self::Diffuse(a.lerp(b, t)),
+            (
+                Self::Hair { color, roughness },
+                Self::Hair {
+                    color: c,
+                    roughness: r,
+                },
+            ) => Self::Hair {
+                color: color.lerp(c, t),
+                roughness: roughness.lerp(r, t),
+            },
+            (Self::Light(a), Self::Light(b)) => Self::Light(a.lerp(b, t)),
+            (Self::Reflector(a), Self::Reflector(b)) => {
+                Self::Reflector(a.lerp(b, t))
+            }
+            (
+                Self::Subsurface {
+                    albedo,
+                    mean_free_path,
+                    ior,
+                },
+                Self::Subsurface {
+                    albedo: a,
+                    mean_free_path: m,
+                    ior: i,
+                },
+            ) => Self::Subsurface {
+                albedo: albedo.lerp(a, t),
+                mean_free_path: mean_free_path.lerp(m, t),
+                ior: ior.lerp(i, t),
+            },
+            _ if t < 0.5 => self.clone(),
+            _ => other.clone(),
         }
     }
 }
@@ -24,6 +24,7 @@ pub fn scatter(albedo: Vector, r: Ray, i: Intersection) -> Option<Scatter> {
     let specular = Ray {
         origin: i.p,
         dir: reflected,
+        time: r.time,
     };
 
     if specular.dir.dot(i.normal) > 0.0 {
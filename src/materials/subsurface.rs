@@ -0,0 +1,57 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::{
+    materials::{dielectric, Scatter},
+    media::Medium,
+    primitives::Intersection,
+    ray::Ray,
+    Vector,
+};
+
+/// Light refracts through the smooth surface of the object in the same
+/// way as with a dielectric. Once inside, it takes a random walk through
+/// the medium given by `medium`.
+pub fn scatter(ior: f32, r: Ray, i: Intersection) -> Option<Scatter> {
+    dielectric::scatter(ior, r, i)
+}
+
+/// Builds the medium inside the object. `albedo` is the color of the
+/// object as a whole after light has bounced around inside of it, which
+/// is converted to the albedo of a single scattering event so that the
+/// rendered color comes out close to what was asked for.
+///
+/// [More info](https://blog.selfshadow.com/publications/s2017-shading-course/imageworks/s2017_pbs_imageworks_slides_v2.pdf)
+pub fn medium(albedo: Vector, mean_free_path: Vector) -> Medium {
+    let mut sigma_a = Vector::zeros();
+    let mut sigma_s = Vector::zeros();
+
+    for c in 0..3 {
+        let a = albedo[c].clamp(0.0, 1.0);
+        let s = 4.09712 + 4.20863 * a
+            - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+        let single_scatter = 1.0 - s * s;
+
+        let sigma_t = 1.0 / mean_free_path[c];
+        sigma_s[c] = single_scatter * sigma_t;
+        sigma_a[c] = sigma_t - sigma_s[c];
+    }
+
+    Medium::Homogeneous {
+        sigma_a,
+        sigma_s,
+        g: 0.0,
+    }
+}
@@ -0,0 +1,30 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::{materials::Scatter, primitives::Intersection, ray::Ray, Vector};
+
+/// The boundary of a volume doesn't interact with light, so the ray
+/// carries on unchanged. The renderer notes that it has entered or left
+/// the medium.
+pub fn scatter(r: Ray, i: Intersection) -> Option<Scatter> {
+    Some(Scatter {
+        specular: Ray {
+            origin: i.p,
+            dir: r.dir,
+            time: r.time,
+        },
+        attenuation: Vector::ones(),
+    })
+}
@@ -70,7 +70,191 @@ pub fn vector_refract(v: Vector, n: Vector, ni_over_nt: f32) -> Option<Vector> {
     }
 }
 
-#[derive(Debug)]
+/// Weighs a sample taken with pdf `f_pdf` against another strategy that
+/// could have produced it with pdf `g_pdf`, for multiple importance
+/// sampling
+///
+/// [More info](https://www.pbr-book.org/3ed-2018/Monte_Carlo_Integration/Importance_Sampling#MultipleImportanceSampling)
+pub fn power_heuristic(f_pdf: f32, g_pdf: f32) -> f32 {
+    let (f, g) = (f_pdf * f_pdf, g_pdf * g_pdf);
+    if f + g > 0.0 {
+        f / (f + g)
+    } else {
+        0.0
+    }
+}
+
+/// Solves `a*x^2 + b*x + c = 0`, returning the real roots in ascending
+/// order. A linear equation yields its single root twice.
+pub fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+
+        let x = -c / b;
+        return Some((x, x));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // Avoids the cancellation in the textbook formula
+    let q = -0.5 * (b + f32::copysign(discriminant.sqrt(), b));
+    let (x0, x1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+
+    if x0 < x1 {
+        Some((x0, x1))
+    } else {
+        Some((x1, x0))
+    }
+}
+
+const ROOT_EPSILON: f64 = 1e-9;
+
+fn is_zero(x: f64) -> bool {
+    x > -ROOT_EPSILON && x < ROOT_EPSILON
+}
+
+/// Solves the normalized quadratic `x^2 + p*x + q = 0`, writing the real
+/// roots into `roots` and returning how many were found
+fn solve_normalized_quadratic(p: f64, q: f64, roots: &mut [f64]) -> usize {
+    let p = p / 2.0;
+    let discriminant = p * p - q;
+
+    if is_zero(discriminant) {
+        roots[0] = -p;
+        1
+    } else if discriminant < 0.0 {
+        0
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        roots[0] = sqrt_d - p;
+        roots[1] = -sqrt_d - p;
+        2
+    }
+}
+
+/// Solves the cubic `c[3]*x^3 + c[2]*x^2 + c[1]*x + c[0] = 0` with
+/// Cardano's method, returning the real roots and how many there are
+pub fn solve_cubic(c: [f64; 4]) -> ([f64; 3], usize) {
+    let mut roots = [0.0; 3];
+
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let c = c[0] / c[3];
+
+    // Substitute x = y - a/3 to eliminate the quadratic term
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + c) / 2.0;
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let count = if is_zero(discriminant) {
+        if is_zero(q) {
+            roots[0] = 0.0;
+            1
+        } else {
+            let u = (-q).cbrt();
+            roots[0] = 2.0 * u;
+            roots[1] = -u;
+            2
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots
+        let phi = (-q / (-cb_p).sqrt()).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        roots[0] = t * phi.cos();
+        roots[1] = -t * (phi + std::f64::consts::PI / 3.0).cos();
+        roots[2] = -t * (phi - std::f64::consts::PI / 3.0).cos();
+        3
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        roots[0] = (sqrt_d - q).cbrt() - (sqrt_d + q).cbrt();
+        1
+    };
+
+    for root in roots.iter_mut().take(count) {
+        *root -= a / 3.0;
+    }
+
+    (roots, count)
+}
+
+/// Solves the quartic `c[4]*x^4 + c[3]*x^3 + c[2]*x^2 + c[1]*x + c[0] = 0`
+/// with Ferrari's method, returning the real roots and how many there are
+pub fn solve_quartic(c: [f64; 5]) -> ([f64; 4], usize) {
+    let mut roots = [0.0; 4];
+
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let d = c[0] / c[4];
+    let c = c[1] / c[4];
+
+    // Substitute x = y - a/4 to eliminate the cubic term
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * c / 4.0 + d;
+
+    let count = if is_zero(r) {
+        // y * (y^3 + p*y + q) = 0
+        let (cubic, n) = solve_cubic([q, p, 0.0, 1.0]);
+        roots[..n].copy_from_slice(&cubic[..n]);
+        roots[n] = 0.0;
+        n + 1
+    } else {
+        // Any real root of the resolvent cubic splits the quartic
+        // into two quadratics
+        let (cubic, _) =
+            solve_cubic([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0]);
+        let z = cubic[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+
+        let u = if is_zero(u) {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return (roots, 0);
+        };
+
+        let v = if is_zero(v) {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return (roots, 0);
+        };
+
+        let v = if q < 0.0 { -v } else { v };
+        let n = solve_normalized_quadratic(v, z - u, &mut roots);
+        n + solve_normalized_quadratic(-v, z + u, &mut roots[n..])
+    };
+
+    for root in roots.iter_mut().take(count) {
+        *root -= a / 4.0;
+
+        // A single Newton step cleans up most of the error introduced
+        // by the resolvent cubic
+        let x = *root;
+        let f = (((x + a) * x + b) * x + c) * x + d;
+        let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+        if df != 0.0 {
+            *root = x - f / df;
+        }
+    }
+
+    (roots, count)
+}
+
+#[derive(Debug, Copy, Clone)]
 pub struct Onb {
     axis: [Vector; 3],
 }
@@ -91,6 +275,12 @@ impl Onb {
     pub fn local(&self, a: Vector) -> Vector {
         a.x * self.u() + a.y * self.v() + a.z * self.w()
     }
+
+    /// The inverse of `local`: expresses a world space vector in
+    /// terms of the basis
+    pub fn to_local(self, a: Vector) -> Vector {
+        Vector::new(a.dot(self.u()), a.dot(self.v()), a.dot(self.w()))
+    }
 }
 
 impl Onb {
@@ -0,0 +1,335 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{phase::sample_henyey_greenstein, MediumSample};
+use crate::{aabb::Aabb, primitives::Block, ray::Ray, Vector};
+
+use rand::prelude::*;
+use std::{fs, path::Path};
+
+/// Densities sampled on a regular grid filling an axis-aligned box.
+/// Samples sit at the centers of the voxels and are blended trilinearly
+/// in between.
+#[derive(Debug)]
+pub struct VoxelGrid {
+    /// Densities with x varying fastest, then y, then z
+    densities: Vec<f32>,
+    /// Number of voxels along the x, y and z axes
+    resolution: (usize, usize, usize),
+    bbox: Aabb,
+    max_density: f32,
+}
+
+impl VoxelGrid {
+    /// Creates a grid spanning `size` from `corner`. The `densities` are
+    /// given with x varying fastest, then y, then z.
+    pub fn new(
+        densities: Vec<f32>,
+        resolution: (usize, usize, usize),
+        corner: Vector,
+        size: Vector,
+    ) -> Self {
+        let (nx, ny, nz) = resolution;
+        assert!(nx > 0 && ny > 0 && nz > 0, "voxel grid can't be empty");
+        assert_eq!(densities.len(), nx * ny * nz, "wrong number of voxels");
+        assert!(
+            densities.iter().all(|&d| d >= 0.0),
+            "densities can't be negative"
+        );
+
+        let max_density = densities.iter().cloned().fold(0.0, f32::max);
+
+        Self {
+            densities,
+            resolution,
+            bbox: Aabb::new(corner, corner + size),
+            max_density,
+        }
+    }
+
+    /// Fills a grid by evaluating `density` at the center of every voxel,
+    /// given as a position from 0 to 1 across the grid
+    pub fn from_fn<F>(
+        resolution: (usize, usize, usize),
+        corner: Vector,
+        size: Vector,
+        density: F,
+    ) -> Self
+    where
+        F: Fn(Vector) -> f32,
+    {
+        let (nx, ny, nz) = resolution;
+        let mut densities = Vec::with_capacity(nx * ny * nz);
+
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    densities.push(density(Vector::new(
+                        (x as f32 + 0.5) / nx as f32,
+                        (y as f32 + 0.5) / ny as f32,
+                        (z as f32 + 0.5) / nz as f32,
+                    )));
+                }
+            }
+        }
+
+        Self::new(densities, resolution, corner, size)
+    }
+
+    /// Loads a grid from a text file holding the number of voxels along
+    /// each axis followed by the densities, with x varying fastest. Values
+    /// are separated by whitespace and `#` starts a comment.
+    pub fn from_file(
+        in_path: &str,
+        corner: Vector,
+        size: Vector,
+    ) -> Result<Self, String> {
+        let path = Path::new(in_path);
+        let display = path.display();
+
+        let text = fs::read_to_string(path)
+            .map_err(|why| format!("Couldn't read {}: {}", display, why))?;
+
+        let mut tokens = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+
+        let mut resolution = [0; 3];
+        for n in resolution.iter_mut() {
+            let token = tokens.next().unwrap_or("");
+            *n = match token.parse::<usize>() {
+                Ok(n) if n > 0 => n,
+                _ => {
+                    return Err(format!(
+                        "Couldn't load {}: invalid resolution {}",
+                        display, token
+                    ))
+                }
+            };
+        }
+
+        let [nx, ny, nz] = resolution;
+        let count = nx * ny * nz;
+        let densities = tokens
+            .take(count)
+            .map(|v| match v.parse::<f32>() {
+                Ok(d) if d >= 0.0 => Ok(d),
+                _ => Err(format!(
+                    "Couldn't load {}: invalid density {}",
+                    display, v
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if densities.len() < count {
+            return Err(format!(
+                "Couldn't load {}: expected {} densities but found {}",
+                display,
+                count,
+                densities.len()
+            ));
+        }
+
+        Ok(Self::new(densities, (nx, ny, nz), corner, size))
+    }
+
+    /// Loads a grid of 8-bit densities with no header, as used by many
+    /// volume datasets. Densities are scaled to [0, 1].
+    pub fn from_raw(
+        in_path: &str,
+        resolution: (usize, usize, usize),
+        corner: Vector,
+        size: Vector,
+    ) -> Result<Self, String> {
+        let path = Path::new(in_path);
+        let display = path.display();
+
+        let data = fs::read(path)
+            .map_err(|why| format!("Couldn't read {}: {}", display, why))?;
+
+        let (nx, ny, nz) = resolution;
+        let count = nx * ny * nz;
+        if data.len() < count {
+            return Err(format!(
+                "Couldn't load {}: expected {} voxels but found {}",
+                display,
+                count,
+                data.len()
+            ));
+        }
+
+        let densities =
+            data.iter().take(count).map(|&d| d as f32 / 255.0).collect();
+
+        Ok(Self::new(densities, resolution, corner, size))
+    }
+
+    /// A block covering the grid, to hold the medium in the scene
+    pub fn container(&self) -> Block {
+        Block::new(self.bbox.min, self.bbox.max)
+    }
+
+    /// The interpolated density at a point in world space. Points outside
+    /// of the grid take the density of the nearest voxel.
+    pub fn density(&self, p: Vector) -> f32 {
+        let (nx, ny, nz) = self.resolution;
+        let size = self.bbox.max - self.bbox.min;
+        let local = (p - self.bbox.min) / size;
+
+        // Position relative to the voxel centers
+        let gx = local.x * nx as f32 - 0.5;
+        let gy = local.y * ny as f32 - 0.5;
+        let gz = local.z * nz as f32 - 0.5;
+
+        let (x0, fx) = split_coord(gx, nx);
+        let (y0, fy) = split_coord(gy, ny);
+        let (z0, fz) = split_coord(gz, nz);
+
+        let voxel = |x: usize, y: usize, z: usize| {
+            self.densities
+                [(z.min(nz - 1) * ny + y.min(ny - 1)) * nx + x.min(nx - 1)]
+        };
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(voxel(x0, y0, z), voxel(x0 + 1, y0, z), fx),
+                lerp(voxel(x0, y0 + 1, z), voxel(x0 + 1, y0 + 1, z), fx),
+                fy,
+            )
+        };
+
+        lerp(plane(z0), plane(z0 + 1), fz)
+    }
+}
+
+/// Splits a grid coordinate into the index of the voxel before it and
+/// the fraction of the way to the next one, clamped to the grid
+fn split_coord(g: f32, n: usize) -> (usize, f32) {
+    let g = g.clamp(0.0, (n - 1) as f32);
+    let i = (g as usize).min(n - 1);
+    (i, g - i as f32)
+}
+
+/// Samples where the ray scatters in the grid with delta tracking.
+/// Tentative collisions are placed with a constant majorant density, and
+/// each one is either a real scattering event or a null collision that
+/// lets the ray carry on. Absorption is accounted for in the weights
+/// rather than by ending the path, which keeps colored media unbiased.
+///
+/// [More info](https://www.pbr-book.org/3ed-2018/Light_Transport_II_Volume_Rendering/Sampling_Volume_Scattering#HeterogeneousMedium)
+pub fn sample(
+    grid: &VoxelGrid,
+    sigma_a: Vector,
+    sigma_s: Vector,
+    g: f32,
+    r: Ray,
+    t_max: f32,
+) -> MediumSample {
+    let mut rng = thread_rng();
+    let sigma_t = sigma_a + sigma_s;
+    let majorant = grid.max_density * sigma_t.x.max(sigma_t.y).max(sigma_t.z);
+
+    let passed = MediumSample {
+        scattered: None,
+        attenuation: Vector::ones(),
+    };
+
+    let (mut t, t_end) = match grid.bbox.clip(r, 0.0, t_max) {
+        Some(range) if majorant > 0.0 => range,
+        _ => return passed,
+    };
+
+    let len = r.dir.length();
+    let mut attenuation = Vector::ones();
+
+    loop {
+        t -= (1.0 - rng.gen::<f32>()).ln() / (majorant * len);
+        if t >= t_end {
+            return MediumSample {
+                scattered: None,
+                attenuation,
+            };
+        }
+
+        let p = r.point_at_parameter(t);
+        let density = grid.density(p);
+        let scatter = sigma_s * density;
+        let null = Vector::ones() * majorant - sigma_t * density;
+
+        let avg = |v: Vector| (v.x + v.y + v.z) / 3.0;
+        let p_scatter = avg(scatter) / (avg(scatter) + avg(null));
+
+        if p_scatter.is_nan() {
+            // Every tentative collision is absorbed
+            return MediumSample {
+                scattered: None,
+                attenuation: Vector::zeros(),
+            };
+        }
+
+        if rng.gen::<f32>() < p_scatter {
+            attenuation *= scatter / (majorant * p_scatter);
+
+            return MediumSample {
+                scattered: Some(Ray {
+                    origin: p,
+                    dir: sample_henyey_greenstein(g, r.dir),
+                    time: r.time,
+                }),
+                attenuation,
+            };
+        }
+
+        attenuation *= null / (majorant * (1.0 - p_scatter));
+    }
+}
+
+/// Estimates the fraction of light that makes it through the grid along
+/// the ray up to `t_max` with ratio tracking. Rather than stopping at the
+/// first real collision, every tentative collision scales the estimate by
+/// the chance that it was a null collision.
+///
+/// [More info](https://www.pbr-book.org/3ed-2018/Light_Transport_II_Volume_Rendering/Sampling_Volume_Scattering#HeterogeneousMedium)
+pub fn transmittance(
+    grid: &VoxelGrid,
+    sigma_a: Vector,
+    sigma_s: Vector,
+    r: Ray,
+    t_max: f32,
+) -> Vector {
+    let mut rng = thread_rng();
+    let sigma_t = sigma_a + sigma_s;
+    let majorant = grid.max_density * sigma_t.x.max(sigma_t.y).max(sigma_t.z);
+
+    let (mut t, t_end) = match grid.bbox.clip(r, 0.0, t_max) {
+        Some(range) if majorant > 0.0 => range,
+        _ => return Vector::ones(),
+    };
+
+    let len = r.dir.length();
+    let mut transmittance = Vector::ones();
+
+    loop {
+        t -= (1.0 - rng.gen::<f32>()).ln() / (majorant * len);
+        if t >= t_end {
+            return transmittance;
+        }
+
+        let density = grid.density(r.point_at_parameter(t));
+        transmittance *= Vector::ones() - sigma_t * (density / majorant);
+    }
+}
@@ -0,0 +1,106 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{phase::sample_henyey_greenstein, MediumSample};
+use crate::{ray::Ray, Vector};
+
+use rand::prelude::*;
+
+/// Samples a free-flight distance through a homogeneous medium. One
+/// color channel is picked at random to sample the distance with, and
+/// the result is weighted by the average pdf over all channels so that
+/// colored media converge without bias.
+pub fn sample(
+    sigma_a: Vector,
+    sigma_s: Vector,
+    g: f32,
+    r: Ray,
+    t_max: f32,
+) -> MediumSample {
+    let mut rng = thread_rng();
+    let sigma_t = sigma_a + sigma_s;
+
+    // Ray directions aren't normalized, so distances in the medium are
+    // measured along the ray and converted back to `t` afterwards
+    let len = r.dir.length();
+    let surface_dist = t_max * len;
+
+    let channel = rng.gen_range(0, 3);
+    let dist = -(1.0 - rng.gen::<f32>()).ln() / sigma_t[channel];
+    let in_medium = dist < surface_dist;
+    let dist = dist.min(surface_dist);
+
+    let mut transmittance = Vector::zeros();
+    for c in 0..3 {
+        transmittance[c] = if sigma_t[c] == 0.0 {
+            1.0
+        } else {
+            (-sigma_t[c] * dist).exp()
+        };
+    }
+
+    let density = if in_medium {
+        sigma_t * transmittance
+    } else {
+        transmittance
+    };
+    let pdf = (density.x + density.y + density.z) / 3.0;
+
+    if pdf == 0.0 {
+        return MediumSample {
+            scattered: None,
+            attenuation: Vector::zeros(),
+        };
+    }
+
+    if in_medium {
+        let scattered = Ray {
+            origin: r.point_at_parameter(dist / len),
+            dir: sample_henyey_greenstein(g, r.dir),
+            time: r.time,
+        };
+
+        MediumSample {
+            scattered: Some(scattered),
+            attenuation: transmittance * sigma_s / pdf,
+        }
+    } else {
+        MediumSample {
+            scattered: None,
+            attenuation: transmittance / pdf,
+        }
+    }
+}
+
+/// The fraction of light that makes it through the medium along the ray
+/// up to `t_max`
+pub fn transmittance(
+    sigma_a: Vector,
+    sigma_s: Vector,
+    r: Ray,
+    t_max: f32,
+) -> Vector {
+    let sigma_t = sigma_a + sigma_s;
+    let dist = t_max * r.dir.length();
+
+    let mut transmittance = Vector::ones();
+    for c in 0..3 {
+        if sigma_t[c] > 0.0 {
+            transmittance[c] = (-sigma_t[c] * dist).exp();
+        }
+    }
+
+    transmittance
+}
@@ -0,0 +1,107 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::{ray::Ray, Vector};
+
+use std::sync::Arc;
+
+mod grid;
+mod homogeneous;
+mod phase;
+
+pub use grid::VoxelGrid;
+
+/// A participating medium that absorbs and scatters light travelling
+/// through it, like fog or smoke. A medium fills the inside of an object
+/// with a `Material::Volume`, or the whole scene as fog.
+#[derive(Debug, Clone)]
+pub enum Medium {
+    /// A medium with the same density everywhere. `sigma_a` and
+    /// `sigma_s` are the absorption and scattering coefficients per unit
+    /// of distance, and `g` is the asymmetry of the Henyey-Greenstein
+    /// phase function, from -1 (back scattering) to 1 (forward
+    /// scattering).
+    Homogeneous {
+        sigma_a: Vector,
+        sigma_s: Vector,
+        g: f32,
+    },
+    /// A medium whose density varies through space, like smoke or a
+    /// cloud. The coefficients are scaled by the density of the grid. The
+    /// grid is placed in world space, so it should be contained by an
+    /// untransformed object such as `VoxelGrid::container`.
+    Grid {
+        grid: Arc<VoxelGrid>,
+        sigma_a: Vector,
+        sigma_s: Vector,
+        g: f32,
+    },
+}
+
+/// The outcome of a ray travelling through a medium
+#[derive(Debug, Copy, Clone)]
+pub struct MediumSample {
+    /// The new ray, if the ray scattered inside the medium before
+    /// reaching the next surface
+    pub scattered: Option<Ray>,
+    pub attenuation: Vector,
+}
+
+impl Medium {
+    /// Samples how far the ray travels through the medium, up to the
+    /// surface at `t_max`
+    pub fn sample(&self, r: Ray, t_max: f32) -> MediumSample {
+        match self {
+            Self::Homogeneous {
+                sigma_a,
+                sigma_s,
+                g,
+            } => homogeneous::sample(*sigma_a, *sigma_s, *g, r, t_max),
+            Self::Grid {
+                grid,
+                sigma_a,
+                sigma_s,
+                g,
+            } => grid::sample(grid, *sigma_a, *sigma_s, *g, r, t_max),
+        }
+    }
+
+    /// The fraction of light that makes it through the medium along the
+    /// ray up to `t_max`
+    pub fn transmittance(&self, r: Ray, t_max: f32) -> Vector {
+        match self {
+            Self::Homogeneous {
+                sigma_a, sigma_s, ..
+            } => homogeneous::transmittance(*sigma_a, *sigma_s, r, t_max),
+            Self::Grid {
+                grid,
+                sigma_a,
+                sigma_s,
+                ..
+            } => grid::transmittance(grid, *sigma_a, *sigma_s, r, t_max),
+        }
+    }
+
+    /// The phase function for light continuing from direction `dir_in`
+    /// into `dir_out` after scattering, which is also the pdf of the
+    /// scattered direction
+    pub fn phase(&self, dir_in: Vector, dir_out: Vector) -> f32 {
+        match self {
+            Self::Homogeneous { g, .. } | Self::Grid { g, .. } => {
+                phase::henyey_greenstein(*g, dir_in, dir_out)
+            }
+        }
+    }
+}
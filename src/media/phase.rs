@@ -0,0 +1,55 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use crate::{math::Onb, Vector};
+
+use rand::prelude::*;
+use std::f32;
+
+/// Samples a new direction from the Henyey-Greenstein phase function
+/// around the direction of travel `dir`. The phase function is sampled
+/// exactly, so the result needs no extra weighting.
+///
+/// [More info](https://www.pbr-book.org/3ed-2018/Light_Transport_II_Volume_Rendering/Sampling_Volume_Scattering#SamplingPhaseFunctions)
+pub fn sample_henyey_greenstein(g: f32, dir: Vector) -> Vector {
+    let mut rng = thread_rng();
+    let (u1, u2) = (rng.gen::<f32>(), rng.gen::<f32>());
+
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * u1
+    } else {
+        let sqr = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+        ((1.0 + g * g - sqr * sqr) / (2.0 * g)).clamp(-1.0, 1.0)
+    };
+
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * f32::consts::PI * u2;
+
+    Onb::build_from_w(dir / dir.length()).local(Vector::new(
+        sin_theta * phi.cos(),
+        sin_theta * phi.sin(),
+        cos_theta,
+    ))
+}
+
+/// The Henyey-Greenstein phase function for light continuing from
+/// direction `dir_in` into `dir_out`. This is also the pdf of
+/// `sample_henyey_greenstein`.
+pub fn henyey_greenstein(g: f32, dir_in: Vector, dir_out: Vector) -> f32 {
+    let cos_theta = dir_in.dot(dir_out) / (dir_in.length() * dir_out.length());
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+
+    (1.0 - g * g) / (4.0 * f32::consts::PI * denom * denom.max(0.0).sqrt())
+}
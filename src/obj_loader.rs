@@ -20,32 +20,27 @@
  * https://github.com/matklad/rustraytracer/blob/946e5c1f7d7dfadf5209c6f505453835bcd12f50/libs/geom/src/shape/mesh/obj_parser.rs
  */
 use crate::{
-    materials::Material,
-    primitives::{Hittable, Triangle},
+    primitives::{Mesh, MeshFace},
     Vector,
 };
 
-use std::{error::Error, fmt, io, num};
+use std::{error::Error, fmt, io};
 
 #[derive(Debug)]
 pub struct ObjLoadError;
 
 impl fmt::Display for ObjLoadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.description().fmt(f)
+        "Failed to load Obj file".fmt(f)
     }
 }
 
-impl Error for ObjLoadError {
-    fn description(&self) -> &str {
-        "Failed to load Obj file"
-    }
-}
+impl Error for ObjLoadError {}
 
 pub struct ObjLoader {
     points: Vec<Vector>,
     normals: Vec<Vector>,
-    faces: Vec<Box<dyn Hittable>>,
+    faces: Vec<MeshFace>,
 }
 
 impl ObjLoader {
@@ -57,29 +52,42 @@ impl ObjLoader {
         }
     }
 
-    pub fn parse<M: 'static + Material + Copy>(
+    /// Reads the triangles of an OBJ file into a mesh, scaling every
+    /// vertex by `scale` and then moving it by `offset`
+    pub fn parse(
         mut self,
         source: &mut dyn io::Read,
-        material: M,
-    ) -> Result<Vec<Box<dyn Hittable>>, Box<dyn Error>> {
+        scale: f32,
+        offset: Vector,
+    ) -> Result<Mesh, Box<dyn Error>> {
         let mut s = String::new();
         source.read_to_string(&mut s)?;
         for line in s.lines() {
             if line.starts_with("v ") {
-                self.parse_vertex(line)?;
+                self.parse_vertex(line, scale, offset)?;
             } else if line.starts_with("vn ") {
                 self.parse_normal(line)?;
             } else if line.starts_with("f ") {
-                self.parse_face(line, material.clone())?;
+                self.parse_face(line)?;
             }
         }
 
-        Ok(self.faces)
+        if self.faces.is_empty() {
+            return Err(Box::new(ObjLoadError));
+        }
+
+        Ok(Mesh::new(self.points, self.normals, Vec::new(), self.faces))
     }
 
-    fn parse_vertex(&mut self, s: &str) -> Result<(), Box<dyn Error>> {
+    fn parse_vertex(
+        &mut self,
+        s: &str,
+        scale: f32,
+        offset: Vector,
+    ) -> Result<(), Box<dyn Error>> {
         let coords = ObjLoader::parse_coordinates(s)?;
-        self.points.push(Vector::new(coords.0, coords.1, coords.2));
+        self.points
+            .push(Vector::new(coords.0, coords.1, coords.2) * scale + offset);
         Ok(())
     }
 
@@ -90,18 +98,14 @@ impl ObjLoader {
         Ok(())
     }
 
-    fn parse_face_simple<M: 'static + Material>(
-        &mut self,
-        s: &str,
-        material: M,
-    ) -> Result<(), Box<dyn Error>> {
+    fn parse_face_simple(&mut self, s: &str) -> Result<(), Box<dyn Error>> {
         let inds = s
             .split_whitespace()
             .skip(1)
             .map(read_index)
             .collect::<Vec<usize>>();
 
-        if inds.len() != 3 {
+        if inds.len() != 3 || inds.iter().any(|&i| i >= self.points.len()) {
             return Err(Box::new(ObjLoadError));
         }
 
@@ -112,21 +116,20 @@ impl ObjLoader {
         );
 
         if are_valid_points(a, b, c) {
-            let f = Triangle::new(a, b, c, 1.0, material);
-            self.faces.push(Box::new(f));
+            self.faces.push(MeshFace {
+                vertices: [inds[0] as u32, inds[1] as u32, inds[2] as u32],
+                normals: None,
+                uvs: None,
+            });
         }
         Ok(())
     }
 
-    fn parse_face_normals<M: 'static + Material>(
-        &mut self,
-        s: &str,
-        material: M,
-    ) -> Result<(), Box<dyn Error>> {
+    fn parse_face_normals(&mut self, s: &str) -> Result<(), Box<dyn Error>> {
         fn read_group(
             s: &str,
         ) -> Result<(usize, usize, usize), Box<dyn Error>> {
-            let inds = s.split('/').map(|s| read_index(s)).collect::<Vec<_>>();
+            let inds = s.split('/').map(read_index).collect::<Vec<_>>();
 
             if inds.len() != 3 {
                 return Err(Box::new(ObjLoadError));
@@ -141,7 +144,11 @@ impl ObjLoader {
             .map(read_group)
             .collect::<Result<Vec<_>, _>>()?;
 
-        if verts.len() != 3 {
+        if verts.len() != 3
+            || verts
+                .iter()
+                .any(|v| v.0 >= self.points.len() || v.2 >= self.normals.len())
+        {
             return Err(Box::new(ObjLoadError));
         }
 
@@ -152,33 +159,28 @@ impl ObjLoader {
         );
 
         if are_valid_points(a, b, c) {
-            let f = Triangle::with_normal(
-                self.points[verts[0].0],
-                self.points[verts[1].0],
-                self.points[verts[2].0],
-                1.0,
-                [
-                    self.normals[verts[0].2],
-                    self.normals[verts[1].2],
-                    self.normals[verts[2].2],
+            self.faces.push(MeshFace {
+                vertices: [
+                    verts[0].0 as u32,
+                    verts[1].0 as u32,
+                    verts[2].0 as u32,
                 ],
-                material,
-            );
-
-            self.faces.push(Box::new(f));
+                normals: Some([
+                    verts[0].2 as u32,
+                    verts[1].2 as u32,
+                    verts[2].2 as u32,
+                ]),
+                uvs: None,
+            });
         }
         Ok(())
     }
 
-    fn parse_face<M: 'static + Material>(
-        &mut self,
-        s: &str,
-        material: M,
-    ) -> Result<(), Box<dyn Error>> {
+    fn parse_face(&mut self, s: &str) -> Result<(), Box<dyn Error>> {
         if s.contains('/') {
-            self.parse_face_normals(s, material)
+            self.parse_face_normals(s)
         } else {
-            self.parse_face_simple(s, material)
+            self.parse_face_simple(s)
         }
     }
 
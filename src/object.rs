@@ -14,8 +14,11 @@
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
 use crate::{
+    aabb::Aabb,
     materials::{Material, Scatter},
+    media::Medium,
     primitives::{Intersection, Primitive, Transformation},
+    ray::Ray,
     Vector,
 };
 
@@ -26,15 +29,46 @@ pub struct Object {
     pub material: Material,
 }
 
+impl Object {
+    pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let intersection = match &self.transformation {
+            Some(t) => t.intersect(&self.primitive, r, t_min, t_max),
+            None => self.primitive.intersect(r, t_min, t_max),
+        };
+
+        intersection.map(|i| Hit {
+            intersection: i,
+            object: self,
+            material: &self.material,
+            scattered: self.material.scatter(r, i),
+            emitted: self.material.emitted(r, i),
+            medium: self.material.medium(r.time),
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        let bbox = self.primitive.bounding_box();
+        match &self.transformation {
+            Some(t) => t.bounding_box(bbox),
+            None => bbox,
+        }
+    }
+}
+
 /// Describes an interaction between an incoming [`Ray`]
 /// and an object.
 ///
 /// [`Ray`]: struct.Ray.html
 #[derive(Debug)]
-pub struct Hit {
+pub struct Hit<'a> {
     // The intersection location
     pub intersection: Intersection,
+    /// The object that was hit
+    pub object: &'a Object,
     /// The material that was hit
+    pub material: &'a Material,
     pub scattered: Option<Scatter>,
     pub emitted: Vector,
+    /// The medium on the inside of the surface that was hit
+    pub medium: Option<Medium>,
 }
@@ -23,14 +23,30 @@ pub struct Block {
 }
 
 impl Block {
+    /// Creates an axis-aligned box spanning the two opposite corners `p0`
+    /// and `p1`, which can be given in any order
     pub fn new(p0: Vector, p1: Vector) -> Self {
+        // The faces expect p0 to be the minimum corner
+        let (p0, p1) = (
+            Vector::new(
+                f32::min(p0.x, p1.x),
+                f32::min(p0.y, p1.y),
+                f32::min(p0.z, p1.z),
+            ),
+            Vector::new(
+                f32::max(p0.x, p1.x),
+                f32::max(p0.y, p1.y),
+                f32::max(p0.z, p1.z),
+            ),
+        );
+
         let objects: [Rectangle; 6] = [
             Rectangle::new(p0.x, p1.x, p0.y, p1.y, p1.z, 1.0, RectPlane::XY),
             Rectangle::new(p0.x, p1.x, p0.y, p1.y, p0.z, -1.0, RectPlane::XY),
-            Rectangle::new(p0.x, p1.x, p0.z, p1.z, p1.y, 1.0, RectPlane::XY),
-            Rectangle::new(p0.x, p1.x, p0.z, p1.z, p0.y, -1.0, RectPlane::XY),
-            Rectangle::new(p0.y, p1.y, p0.z, p1.z, p1.x, 1.0, RectPlane::XY),
-            Rectangle::new(p0.y, p1.y, p0.z, p1.z, p0.x, -1.0, RectPlane::XY),
+            Rectangle::new(p0.x, p1.x, p0.z, p1.z, p1.y, 1.0, RectPlane::XZ),
+            Rectangle::new(p0.x, p1.x, p0.z, p1.z, p0.y, -1.0, RectPlane::XZ),
+            Rectangle::new(p0.y, p1.y, p0.z, p1.z, p1.x, 1.0, RectPlane::YZ),
+            Rectangle::new(p0.y, p1.y, p0.z, p1.z, p0.x, -1.0, RectPlane::YZ),
         ];
 
         Self {
@@ -62,3 +78,111 @@ impl Block {
         self.bbox
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    fn unit_block() -> Block {
+        Block::new(Vector::new(-1.0, -1.0, -1.0), Vector::new(1.0, 1.0, 1.0))
+    }
+
+    fn axes() -> [Vector; 6] {
+        [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(0.0, 0.0, -1.0),
+        ]
+    }
+
+    fn assert_close(a: Vector, b: Vector) {
+        assert!((a - b).length() < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hits_each_face_from_outside() {
+        let block = unit_block();
+
+        for &axis in &axes() {
+            // Slightly off center so the ray does not run along an edge
+            let offset = Vector::new(0.1, 0.2, 0.3);
+            let r = Ray {
+                origin: axis * 5.0 + offset - axis * axis.dot(offset),
+                dir: -axis,
+                time: 0.0,
+            };
+
+            let hit = block
+                .intersect(r, 0.0, f32::MAX)
+                .unwrap_or_else(|| panic!("missed the face facing {:?}", axis));
+
+            assert!((hit.t - 4.0).abs() < EPSILON);
+            assert_close(hit.normal, axis);
+            assert_close(hit.p, r.origin + 4.0 * r.dir);
+        }
+    }
+
+    #[test]
+    fn hits_each_face_from_inside() {
+        let block = unit_block();
+
+        for &axis in &axes() {
+            let r = Ray {
+                origin: Vector::new(0.1, 0.2, 0.3) * 0.5,
+                dir: axis,
+                time: 0.0,
+            };
+
+            let hit = block
+                .intersect(r, 0.0, f32::MAX)
+                .unwrap_or_else(|| panic!("missed the face facing {:?}", axis));
+
+            // Dielectrics rely on the normal pointing out of the block to
+            // tell that the ray is leaving it
+            assert_close(hit.normal, axis);
+            assert!(hit.normal.dot(r.dir) > 0.0);
+            assert!((hit.p.dot(axis) - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn misses_rays_passing_by() {
+        let block = unit_block();
+
+        for &axis in &axes() {
+            let side = Vector::new(axis.y, axis.z, axis.x);
+            let r = Ray {
+                origin: axis * 5.0 + side * 1.5,
+                dir: -axis,
+                time: 0.0,
+            };
+
+            assert!(block.intersect(r, 0.0, f32::MAX).is_none());
+        }
+    }
+
+    #[test]
+    fn accepts_corners_in_any_order() {
+        let block = Block::new(
+            Vector::new(1.0, -1.0, 1.0),
+            Vector::new(-1.0, 1.0, -1.0),
+        );
+        let bbox = block.bounding_box();
+
+        assert_close(bbox.min, Vector::new(-1.0, -1.0, -1.0));
+        assert_close(bbox.max, Vector::new(1.0, 1.0, 1.0));
+
+        let r = Ray {
+            origin: Vector::new(0.2, 5.0, 0.1),
+            dir: Vector::new(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = block.intersect(r, 0.0, f32::MAX).unwrap();
+        assert_close(hit.normal, Vector::new(0.0, 1.0, 0.0));
+    }
+}
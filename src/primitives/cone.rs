@@ -0,0 +1,135 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{disk::azimuth, disk::disk_extent, Intersection};
+use crate::{
+    aabb::Aabb,
+    math::{solve_quadratic, Onb},
+    ray::Ray,
+    Vector,
+};
+
+/// Keeps the apex from being flush against the bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A cone with a circular base tapering to a point at `apex`, optionally
+/// closed off with a disk at the base
+#[derive(Debug)]
+pub struct Cone {
+    base: Vector,
+    height: f32,
+    radius: f32,
+    capped: bool,
+    basis: Onb,
+    bbox: Aabb,
+}
+
+impl Cone {
+    pub fn new(base: Vector, apex: Vector, radius: f32, capped: bool) -> Self {
+        let axis = apex - base;
+        let basis = Onb::build_from_w(axis);
+        let extent = disk_extent(basis.w(), radius);
+
+        Self {
+            base,
+            height: axis.length(),
+            radius,
+            capped,
+            basis,
+            bbox: Aabb::surrounding_box(
+                Aabb::new(base - extent, base + extent),
+                Aabb::new(apex - BBOX_PADDING, apex + BBOX_PADDING),
+            ),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Work in a frame where the base is centered on the origin and
+        // the apex sits on the z axis, so the surface is given by
+        // x^2 + y^2 = k^2 * (height - z)^2
+        let o = self.basis.to_local(r.origin - self.base);
+        let d = self.basis.to_local(r.dir);
+        let k = self.radius / self.height;
+        let k_sq = k * k;
+        let h = self.height - o.z;
+
+        let mut result = None;
+        let mut closest_so_far = t_max;
+
+        if let Some((t0, t1)) = solve_quadratic(
+            d.x * d.x + d.y * d.y - k_sq * d.z * d.z,
+            2.0 * (o.x * d.x + o.y * d.y + k_sq * h * d.z),
+            o.x * o.x + o.y * o.y - k_sq * h * h,
+        ) {
+            // The equation also describes the mirrored cone above the
+            // apex, which the height check filters out
+            for &t in &[t0, t1] {
+                let z = o.z + t * d.z;
+                if t > t_min
+                    && t < closest_so_far
+                    && z >= 0.0
+                    && z <= self.height
+                {
+                    let x = o.x + t * d.x;
+                    let y = o.y + t * d.y;
+                    result = Some((
+                        t,
+                        azimuth(x, y),
+                        z / self.height,
+                        Vector::new(x, y, k_sq * (self.height - z)).normalize(),
+                    ));
+                    closest_so_far = t;
+                    break;
+                }
+            }
+        }
+
+        if self.capped {
+            let t = -o.z / d.z;
+            if t > t_min && t < closest_so_far {
+                let x = o.x + t * d.x;
+                let y = o.y + t * d.y;
+                let dist_sq = x * x + y * y;
+
+                if dist_sq <= self.radius * self.radius {
+                    result = Some((
+                        t,
+                        azimuth(x, y),
+                        dist_sq.sqrt() / self.radius,
+                        Vector::new(0.0, 0.0, -1.0),
+                    ));
+                }
+            }
+        }
+
+        result.map(|(t, u, v, normal)| Intersection {
+            u,
+            v,
+            t,
+            p: r.point_at_parameter(t),
+            normal: self.basis.local(normal),
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,245 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{Intersection, Primitive};
+use crate::{aabb::Aabb, ray::Ray};
+
+use std::cmp::Ordering;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Space inside either operand
+    Union,
+    /// Space inside both operands
+    Intersection,
+    /// Space inside the left operand but not the right one
+    Difference,
+}
+
+/// A stretch along a ray that lies inside a closed primitive. Spans
+/// that extend past the queried range are cut off at its ends.
+#[derive(Debug, Copy, Clone)]
+pub struct Span {
+    pub enter: Intersection,
+    pub exit: Intersection,
+}
+
+/// Constructive solid geometry node combining two closed primitives
+#[derive(Debug)]
+pub struct Csg {
+    operation: CsgOperation,
+    left: Box<Primitive>,
+    right: Box<Primitive>,
+    bbox: Aabb,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: Primitive,
+        right: Primitive,
+    ) -> Self {
+        let (l, r) = (left.bounding_box(), right.bounding_box());
+        let bbox = match operation {
+            CsgOperation::Union => Aabb::surrounding_box(l, r),
+            CsgOperation::Intersection => Aabb::overlap(l, r),
+            CsgOperation::Difference => l,
+        };
+
+        Self {
+            operation,
+            left: Box::new(left),
+            right: Box::new(right),
+            bbox,
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Boundaries sitting exactly on the ends of the range are only
+        // there because the spans were cut off
+        for s in self.spans(r, t_min, t_max) {
+            for i in &[s.enter, s.exit] {
+                if i.t > t_min && i.t < t_max {
+                    return Some(*i);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn spans(&self, r: Ray, t_min: f32, t_max: f32) -> Vec<Span> {
+        let left = self.left.spans(r, t_min, t_max);
+        let right = self.right.spans(r, t_min, t_max);
+
+        let mut events = Vec::with_capacity(2 * (left.len() + right.len()));
+        for (spans, is_left) in &[(left, true), (right, false)] {
+            for s in spans {
+                events.push((s.enter, *is_left, true));
+                events.push((s.exit, *is_left, false));
+            }
+        }
+
+        events.sort_by(|a, b| {
+            a.0.t.partial_cmp(&b.0.t).unwrap_or(Ordering::Equal)
+        });
+
+        let mut result = Vec::new();
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut enter = None;
+
+        for (mut i, is_left, entering) in events {
+            if is_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+
+            let inside = match self.operation {
+                CsgOperation::Union => in_left || in_right,
+                CsgOperation::Intersection => in_left && in_right,
+                CsgOperation::Difference => in_left && !in_right,
+            };
+
+            // Surfaces carved out of the left operand face the
+            // other way
+            if self.operation == CsgOperation::Difference && !is_left {
+                i.normal = -i.normal;
+            }
+
+            match enter {
+                None if inside => enter = Some(i),
+                Some(e) if !inside => {
+                    result.push(Span { enter: e, exit: i });
+                    enter = None;
+                }
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitives::Sphere, Vector};
+
+    const EPSILON: f32 = 0.0001;
+
+    fn sphere(x: f32, radius: f32) -> Primitive {
+        Primitive::Sphere(Sphere::new(Vector::new(x, 0.0, 0.0), radius))
+    }
+
+    fn along_x() -> Ray {
+        Ray {
+            origin: Vector::new(-5.0, 0.0, 0.0),
+            dir: Vector::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        }
+    }
+
+    fn bounds(spans: &[Span]) -> Vec<(f32, f32)> {
+        spans.iter().map(|s| (s.enter.t, s.exit.t)).collect()
+    }
+
+    fn assert_spans(spans: &[Span], expected: &[(f32, f32)]) {
+        let found = bounds(spans);
+        assert_eq!(found.len(), expected.len(), "{:?}", found);
+
+        for (&(enter, exit), &(e, x)) in found.iter().zip(expected) {
+            assert!(
+                (enter - e).abs() < EPSILON && (exit - x).abs() < EPSILON,
+                "{:?} != {:?}",
+                found,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn merges_overlapping_spans() {
+        // The spheres cover [4, 6] and [5, 7] along the ray
+        let r = along_x();
+        let csg = |op| Csg::new(op, sphere(0.0, 1.0), sphere(1.0, 1.0));
+
+        let union = csg(CsgOperation::Union).spans(r, 0.0, f32::MAX);
+        assert_spans(&union, &[(4.0, 7.0)]);
+
+        let intersection =
+            csg(CsgOperation::Intersection).spans(r, 0.0, f32::MAX);
+        assert_spans(&intersection, &[(5.0, 6.0)]);
+
+        let difference = csg(CsgOperation::Difference).spans(r, 0.0, f32::MAX);
+        assert_spans(&difference, &[(4.0, 5.0)]);
+    }
+
+    #[test]
+    fn difference_flips_carved_normals() {
+        let r = along_x();
+        let csg = Csg::new(
+            CsgOperation::Difference,
+            sphere(0.0, 1.0),
+            sphere(1.0, 1.0),
+        );
+
+        // The exit is where the ray enters the right sphere, so its
+        // normal has to point out of the remaining solid
+        let spans = csg.spans(r, 0.0, f32::MAX);
+        assert!(spans[0].enter.normal.dot(r.dir) < 0.0);
+        assert!(spans[0].exit.normal.dot(r.dir) > 0.0);
+    }
+
+    #[test]
+    fn difference_splits_spans() {
+        // A small sphere carved out of the middle of a large one
+        let r = along_x();
+        let csg = Csg::new(
+            CsgOperation::Difference,
+            sphere(0.0, 2.0),
+            sphere(0.0, 0.5),
+        );
+
+        let spans = csg.spans(r, 0.0, f32::MAX);
+        assert_spans(&spans, &[(3.0, 4.5), (5.5, 7.0)]);
+
+        let hit = csg.intersect(r, 4.0, f32::MAX).unwrap();
+        assert!((hit.t - 4.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection_of_disjoint_operands_is_empty() {
+        let r = along_x();
+        let csg = Csg::new(
+            CsgOperation::Intersection,
+            sphere(-2.0, 1.0),
+            sphere(2.0, 1.0),
+        );
+
+        assert!(csg.spans(r, 0.0, f32::MAX).is_empty());
+        assert!(csg.intersect(r, 0.0, f32::MAX).is_none());
+    }
+}
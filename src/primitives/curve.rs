@@ -0,0 +1,370 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::Intersection;
+use crate::{aabb::Aabb, ray::Ray, Vector};
+
+/// Upper bound on how often a curve is split in half while searching for
+/// an intersection
+const MAX_DEPTH: usize = 10;
+
+/// How a curve is shaded across its width
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CurveType {
+    /// A flat ribbon that always faces the incoming ray, for thin strands
+    /// like grass blades seen from afar
+    Flat,
+    /// A ribbon whose normal curves around the tangent like a cylinder
+    /// would, which is cheaper than a true tube and looks the same once
+    /// the strand is only a few pixels wide
+    Cylinder,
+}
+
+/// A cubic Bézier curve with a width that varies linearly along it, for
+/// rendering hair, fur and grass. Long curves should be [`split`] into
+/// segments so they get tight bounding boxes in the [`Bvh`].
+///
+/// [`split`]: #method.split
+/// [`Bvh`]: ../bvh/struct.Bvh.html
+#[derive(Debug)]
+pub struct Curve {
+    control_points: [Vector; 4],
+    /// Width at the start and end of the curve
+    width: (f32, f32),
+    /// The part of the original curve this segment covers
+    u_range: (f32, f32),
+    kind: CurveType,
+    /// Number of times to subdivide the curve when intersecting it
+    depth: usize,
+    bbox: Aabb,
+}
+
+impl Curve {
+    pub fn new(
+        control_points: [Vector; 4],
+        width: (f32, f32),
+        kind: CurveType,
+    ) -> Self {
+        Self::segment(control_points, width, (0.0, 1.0), kind)
+    }
+
+    /// Splits the curve into `segments` pieces of equal parameter length
+    pub fn split(&self, segments: usize) -> Vec<Curve> {
+        let cp = &self.control_points;
+        let (w0, w1) = self.width;
+        let (u0, u1) = self.u_range;
+
+        (0..segments)
+            .map(|i| {
+                let a = i as f32 / segments as f32;
+                let b = (i + 1) as f32 / segments as f32;
+
+                Self::segment(
+                    [
+                        blossom(cp, a, a, a),
+                        blossom(cp, a, a, b),
+                        blossom(cp, a, b, b),
+                        blossom(cp, b, b, b),
+                    ],
+                    (lerp(a, w0, w1), lerp(b, w0, w1)),
+                    (lerp(a, u0, u1), lerp(b, u0, u1)),
+                    self.kind,
+                )
+            })
+            .collect()
+    }
+
+    fn segment(
+        control_points: [Vector; 4],
+        width: (f32, f32),
+        u_range: (f32, f32),
+        kind: CurveType,
+    ) -> Self {
+        let cp = &control_points;
+        let half_width = f32::max(width.0, width.1) / 2.0;
+
+        // The curve lies within the convex hull of its control points
+        let mut min = cp[0];
+        let mut max = cp[0];
+        for p in &cp[1..] {
+            for a in 0..3 {
+                min[a] = f32::min(min[a], p[a]);
+                max[a] = f32::max(max[a], p[a]);
+            }
+        }
+
+        // Subdivide until the segments are close enough to straight lines,
+        // going by the second differences of the control points
+        let mut l0 = 0.0;
+        for i in 0..2 {
+            let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+            l0 = f32::max(
+                l0,
+                f32::max(d.x.abs(), f32::max(d.y.abs(), d.z.abs())),
+            );
+        }
+
+        let eps = f32::max(width.0, width.1) * 0.05;
+        let depth = if l0 > 0.0 && eps > 0.0 {
+            let r0 =
+                f32::log2(std::f32::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps));
+            usize::min(f32::max(r0 / 2.0, 0.0).ceil() as usize, MAX_DEPTH)
+        } else {
+            0
+        };
+
+        Self {
+            control_points,
+            width,
+            u_range,
+            kind,
+            depth,
+            bbox: Aabb::new(min - half_width, max + half_width),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Move to a frame where the ray starts at the origin and points
+        // down the z axis, so hits are points on the curve near the z axis
+        let dir_len = r.dir.length();
+        let (x_axis, y_axis, z_axis) = ray_frame(r.dir / dir_len);
+        let to_ray = |p: Vector| {
+            let p = p - r.origin;
+            Vector::new(p.dot(x_axis), p.dot(y_axis), p.dot(z_axis))
+        };
+
+        let cp = [
+            to_ray(self.control_points[0]),
+            to_ray(self.control_points[1]),
+            to_ray(self.control_points[2]),
+            to_ray(self.control_points[3]),
+        ];
+
+        let (z, w, v) = self.recursive_intersect(
+            &cp,
+            (0.0, 1.0),
+            (t_min * dir_len, t_max * dir_len),
+            self.depth,
+        )?;
+
+        let t = z / dir_len;
+        let (_, tangent) = bezier(&self.control_points, w);
+        let tangent = tangent / tangent.length();
+
+        // The ribbon faces back along the ray, across the curve
+        let facing = -r.dir / dir_len;
+        let flat = facing - tangent * facing.dot(tangent);
+        let flat = flat / flat.length();
+
+        let normal = match self.kind {
+            CurveType::Flat => flat,
+            CurveType::Cylinder => {
+                let theta = (v - 0.5) * std::f32::consts::PI;
+                let side = tangent.cross(flat);
+                theta.cos() * flat + theta.sin() * side
+            }
+        };
+
+        Some(Intersection {
+            u: lerp(w, self.u_range.0, self.u_range.1),
+            v,
+            t,
+            p: r.point_at_parameter(t),
+            normal,
+            tangent: Some(tangent),
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Finds the closest hit on the part `u` of the curve in ray space,
+    /// where `cp` are the control points of that part. Returns the
+    /// distance along the ray, the curve parameter and the position across
+    /// the width of the curve.
+    fn recursive_intersect(
+        &self,
+        cp: &[Vector; 4],
+        u: (f32, f32),
+        z: (f32, f32),
+        depth: usize,
+    ) -> Option<(f32, f32, f32)> {
+        let half_width = f32::max(
+            lerp(u.0, self.width.0, self.width.1),
+            lerp(u.1, self.width.0, self.width.1),
+        ) / 2.0;
+
+        // Skip this part if its bounds miss the ray, which runs along the
+        // z axis
+        let (mut lo, mut hi) = (cp[0], cp[0]);
+        for p in &cp[1..] {
+            for a in 0..3 {
+                lo[a] = f32::min(lo[a], p[a]);
+                hi[a] = f32::max(hi[a], p[a]);
+            }
+        }
+
+        if lo.x - half_width > 0.0
+            || hi.x + half_width < 0.0
+            || lo.y - half_width > 0.0
+            || hi.y + half_width < 0.0
+            || lo.z - half_width > z.1
+            || hi.z + half_width < z.0
+        {
+            return None;
+        }
+
+        if depth > 0 {
+            let (first, second) = subdivide(cp);
+            let mid = (u.0 + u.1) / 2.0;
+
+            return match self.recursive_intersect(
+                &first,
+                (u.0, mid),
+                z,
+                depth - 1,
+            ) {
+                Some(hit) => Some(
+                    self.recursive_intersect(
+                        &second,
+                        (mid, u.1),
+                        (z.0, hit.0),
+                        depth - 1,
+                    )
+                    .unwrap_or(hit),
+                ),
+                None => {
+                    self.recursive_intersect(&second, (mid, u.1), z, depth - 1)
+                }
+            };
+        }
+
+        // Treat the remaining part as a line segment, ignoring hits that
+        // fall before its start or past its end
+        let edge_start =
+            (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        let edge_end =
+            (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if edge_start < 0.0 || edge_end < 0.0 {
+            return None;
+        }
+
+        let (sx, sy) = (cp[3].x - cp[0].x, cp[3].y - cp[0].y);
+        let denom = sx * sx + sy * sy;
+        if denom == 0.0 {
+            return None;
+        }
+
+        let w = (-cp[0].x * sx - cp[0].y * sy) / denom;
+        let w_curve = lerp(w, u.0, u.1).clamp(u.0, u.1);
+        let hit_width = lerp(w_curve, self.width.0, self.width.1);
+
+        let (pc, dpcdw) = bezier(cp, w.clamp(0.0, 1.0));
+        let dist2 = pc.x * pc.x + pc.y * pc.y;
+        if dist2 > hit_width * hit_width / 4.0 || pc.z <= z.0 || pc.z >= z.1 {
+            return None;
+        }
+
+        // Which side of the curve the hit is on decides the direction
+        // across its width
+        let dist = dist2.sqrt();
+        let v = if dpcdw.x * -pc.y + pc.x * dpcdw.y > 0.0 {
+            0.5 + dist / hit_width
+        } else {
+            0.5 - dist / hit_width
+        };
+
+        Some((pc.z, w_curve, v))
+    }
+}
+
+/// An orthonormal frame with its z axis along the (unit length) `dir`
+fn ray_frame(dir: Vector) -> (Vector, Vector, Vector) {
+    let a = if dir.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+
+    let y = dir.cross(a);
+    let y = y / y.length();
+    (dir.cross(y), y, dir)
+}
+
+/// Evaluates the curve and its derivative at `u`
+fn bezier(cp: &[Vector; 4], u: f32) -> (Vector, Vector) {
+    let a = [
+        lerp_vector(u, cp[0], cp[1]),
+        lerp_vector(u, cp[1], cp[2]),
+        lerp_vector(u, cp[2], cp[3]),
+    ];
+    let b = [lerp_vector(u, a[0], a[1]), lerp_vector(u, a[1], a[2])];
+
+    // The derivative vanishes at the ends when control points coincide
+    let derivative = if (b[1] - b[0]).length_squared() > 0.0 {
+        3.0 * (b[1] - b[0])
+    } else {
+        cp[3] - cp[0]
+    };
+
+    (lerp_vector(u, b[0], b[1]), derivative)
+}
+
+/// Splits the curve at its midpoint
+fn subdivide(cp: &[Vector; 4]) -> ([Vector; 4], [Vector; 4]) {
+    let mid = (cp[0] + 3.0 * cp[1] + 3.0 * cp[2] + cp[3]) / 8.0;
+
+    (
+        [
+            cp[0],
+            (cp[0] + cp[1]) / 2.0,
+            (cp[0] + 2.0 * cp[1] + cp[2]) / 4.0,
+            mid,
+        ],
+        [
+            mid,
+            (cp[1] + 2.0 * cp[2] + cp[3]) / 4.0,
+            (cp[2] + cp[3]) / 2.0,
+            cp[3],
+        ],
+    )
+}
+
+/// The blossom of the curve, which gives the control points of any part
+/// of it
+fn blossom(cp: &[Vector; 4], u0: f32, u1: f32, u2: f32) -> Vector {
+    let a = [
+        lerp_vector(u0, cp[0], cp[1]),
+        lerp_vector(u0, cp[1], cp[2]),
+        lerp_vector(u0, cp[2], cp[3]),
+    ];
+    let b = [lerp_vector(u1, a[0], a[1]), lerp_vector(u1, a[1], a[2])];
+
+    lerp_vector(u2, b[0], b[1])
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn lerp_vector(t: f32, a: Vector, b: Vector) -> Vector {
+    a + t * (b - a)
+}
@@ -0,0 +1,129 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{disk::azimuth, disk::disk_extent, Intersection};
+use crate::{
+    aabb::Aabb,
+    math::{solve_quadratic, Onb},
+    ray::Ray,
+    Vector,
+};
+
+/// A cylinder running from `base` to `top`, optionally closed off with
+/// a disk at either end
+#[derive(Debug)]
+pub struct Cylinder {
+    base: Vector,
+    height: f32,
+    radius: f32,
+    capped: bool,
+    basis: Onb,
+    bbox: Aabb,
+}
+
+impl Cylinder {
+    pub fn new(base: Vector, top: Vector, radius: f32, capped: bool) -> Self {
+        let axis = top - base;
+        let basis = Onb::build_from_w(axis);
+        let extent = disk_extent(basis.w(), radius);
+
+        Self {
+            base,
+            height: axis.length(),
+            radius,
+            capped,
+            basis,
+            bbox: Aabb::surrounding_box(
+                Aabb::new(base - extent, base + extent),
+                Aabb::new(top - extent, top + extent),
+            ),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Work in a frame where the cylinder runs up the z axis from
+        // the origin
+        let o = self.basis.to_local(r.origin - self.base);
+        let d = self.basis.to_local(r.dir);
+
+        let mut result = None;
+        let mut closest_so_far = t_max;
+
+        if let Some((t0, t1)) = solve_quadratic(
+            d.x * d.x + d.y * d.y,
+            2.0 * (o.x * d.x + o.y * d.y),
+            o.x * o.x + o.y * o.y - self.radius * self.radius,
+        ) {
+            for &t in &[t0, t1] {
+                let z = o.z + t * d.z;
+                if t > t_min
+                    && t < closest_so_far
+                    && z >= 0.0
+                    && z <= self.height
+                {
+                    let x = o.x + t * d.x;
+                    let y = o.y + t * d.y;
+                    result = Some((
+                        t,
+                        azimuth(x, y),
+                        z / self.height,
+                        Vector::new(x, y, 0.0) / self.radius,
+                    ));
+                    closest_so_far = t;
+                    break;
+                }
+            }
+        }
+
+        if self.capped {
+            for &(z, nz) in &[(0.0, -1.0), (self.height, 1.0)] {
+                let t = (z - o.z) / d.z;
+                if t > t_min && t < closest_so_far {
+                    let x = o.x + t * d.x;
+                    let y = o.y + t * d.y;
+                    let dist_sq = x * x + y * y;
+
+                    if dist_sq <= self.radius * self.radius {
+                        result = Some((
+                            t,
+                            azimuth(x, y),
+                            dist_sq.sqrt() / self.radius,
+                            Vector::new(0.0, 0.0, nz),
+                        ));
+                        closest_so_far = t;
+                    }
+                }
+            }
+        }
+
+        result.map(|(t, u, v, normal)| Intersection {
+            u,
+            v,
+            t,
+            p: r.point_at_parameter(t),
+            normal: self.basis.local(normal),
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
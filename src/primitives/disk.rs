@@ -0,0 +1,101 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::Intersection;
+use crate::{aabb::Aabb, math::Onb, ray::Ray, Vector};
+
+use std::f32::consts::PI;
+
+/// Keeps axis-aligned disks from getting a zero-thickness bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A flat circular disk facing in the direction of its normal
+#[derive(Debug)]
+pub struct Disk {
+    center: Vector,
+    radius: f32,
+    basis: Onb,
+    bbox: Aabb,
+}
+
+impl Disk {
+    pub fn new(center: Vector, normal: Vector, radius: f32) -> Self {
+        let basis = Onb::build_from_w(normal);
+        let extent = disk_extent(basis.w(), radius) + BBOX_PADDING;
+
+        Self {
+            center,
+            radius,
+            basis,
+            bbox: Aabb::new(center - extent, center + extent),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let o = self.basis.to_local(r.origin - self.center);
+        let d = self.basis.to_local(r.dir);
+
+        let t = -o.z / d.z;
+        if t < t_min || t > t_max || t.is_nan() {
+            return None;
+        }
+
+        let x = o.x + t * d.x;
+        let y = o.y + t * d.y;
+        let dist_sq = x * x + y * y;
+
+        if dist_sq > self.radius * self.radius {
+            return None;
+        }
+
+        Some(Intersection {
+            u: azimuth(x, y),
+            v: dist_sq.sqrt() / self.radius,
+            t,
+            p: r.point_at_parameter(t),
+            normal: self.basis.w(),
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Computes how far a disk with the given unit normal and radius
+/// extends from its center along each axis
+pub(super) fn disk_extent(normal: Vector, radius: f32) -> Vector {
+    Vector::new(
+        radius * f32::sqrt(f32::max(0.0, 1.0 - normal.x * normal.x)),
+        radius * f32::sqrt(f32::max(0.0, 1.0 - normal.y * normal.y)),
+        radius * f32::sqrt(f32::max(0.0, 1.0 - normal.z * normal.z)),
+    )
+}
+
+/// Maps the angle of the point (x, y) around the origin to [0, 1]
+pub(super) fn azimuth(x: f32, y: f32) -> f32 {
+    let phi = f32::atan2(y, x);
+    if phi < 0.0 {
+        (phi + 2.0 * PI) / (2.0 * PI)
+    } else {
+        phi / (2.0 * PI)
+    }
+}
@@ -0,0 +1,333 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{triangle::moller_trumbore, Intersection};
+use crate::{aabb::Aabb, image::Image, ray::Ray, Vector};
+
+/// Lattice cells across the field for the first octave of noise
+const NOISE_FREQUENCY: f32 = 4.0;
+
+/// Flat fields would otherwise get a zero-thickness bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A terrain defined by a regular grid of heights over the XZ plane. Each
+/// grid cell is made of two triangles, which are only built while a ray
+/// passes over the cell, so large grids take up little memory.
+#[derive(Debug)]
+pub struct Heightfield {
+    /// World space heights, row by row along the x axis
+    heights: Vec<f32>,
+    /// Per-sample normals, used to smooth out the triangles
+    normals: Vec<Vector>,
+    /// Number of samples along the x and z axes
+    resolution: (usize, usize),
+    corner: Vector,
+    /// Size of a single grid cell along the x and z axes
+    cell_size: (f32, f32),
+    bbox: Aabb,
+}
+
+impl Heightfield {
+    /// Creates a heightfield spanning `size.x` by `size.z` from `corner`.
+    /// The `heights` are given row by row along the x axis, and are
+    /// scaled by `size.y`.
+    pub fn new(
+        heights: Vec<f32>,
+        resolution: (usize, usize),
+        corner: Vector,
+        size: Vector,
+    ) -> Self {
+        let (nx, nz) = resolution;
+        assert!(nx >= 2 && nz >= 2, "heightfield needs at least 2x2 samples");
+        assert_eq!(heights.len(), nx * nz, "wrong number of heights");
+
+        let heights: Vec<f32> =
+            heights.into_iter().map(|h| corner.y + h * size.y).collect();
+        let cell_size = (size.x / (nx - 1) as f32, size.z / (nz - 1) as f32);
+
+        // Central differences in the interior, one sided at the edges
+        let normals = (0..nz)
+            .flat_map(|z| (0..nx).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let (x0, x1) = (x.saturating_sub(1), usize::min(x + 1, nx - 1));
+                let (z0, z1) = (z.saturating_sub(1), usize::min(z + 1, nz - 1));
+
+                let dx = (heights[z * nx + x1] - heights[z * nx + x0])
+                    / ((x1 - x0) as f32 * cell_size.0);
+                let dz = (heights[z1 * nx + x] - heights[z0 * nx + x])
+                    / ((z1 - z0) as f32 * cell_size.1);
+
+                Vector::new(-dx, 1.0, -dz).normalize()
+            })
+            .collect();
+
+        let (low, high) =
+            heights.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &h| {
+                (f32::min(lo, h), f32::max(hi, h))
+            });
+
+        let bbox = Aabb::new(
+            Vector::new(corner.x, low, corner.z) - BBOX_PADDING,
+            Vector::new(corner.x + size.x, high, corner.z + size.z)
+                + BBOX_PADDING,
+        );
+
+        Self {
+            heights,
+            normals,
+            resolution,
+            corner,
+            cell_size,
+            bbox,
+        }
+    }
+
+    /// Creates a heightfield from the luminance of an image, with one
+    /// sample per pixel. The top row of the image lies along `corner.z`.
+    pub fn from_image(image: &Image, corner: Vector, size: Vector) -> Self {
+        let heights = (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+            .map(|(x, y)| image.luminance(x, y))
+            .collect();
+
+        Self::new(heights, (image.width, image.height), corner, size)
+    }
+
+    /// Creates a heightfield from fractal value noise. The same `seed`
+    /// always produces the same terrain.
+    pub fn from_noise(
+        resolution: (usize, usize),
+        octaves: u32,
+        seed: u32,
+        corner: Vector,
+        size: Vector,
+    ) -> Self {
+        let (nx, nz) = resolution;
+        let heights = (0..nz)
+            .flat_map(|z| (0..nx).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                fbm(
+                    NOISE_FREQUENCY * x as f32 / (nx - 1) as f32,
+                    NOISE_FREQUENCY * z as f32 / (nz - 1) as f32,
+                    octaves,
+                    seed,
+                )
+            })
+            .collect();
+
+        Self::new(heights, resolution, corner, size)
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let (t_enter, t_exit) = self.bbox.clip(r, t_min, t_max)?;
+        let (nx, nz) = self.resolution;
+        let (cx, cz) = self.cell_size;
+
+        // Walk the cells under the ray in order, starting from the one
+        // where it enters the bounding box
+        let o = r.origin - self.corner;
+        let entry = o + t_enter * r.dir;
+        let mut x = usize::min((f32::max(entry.x / cx, 0.0)) as usize, nx - 2);
+        let mut z = usize::min((f32::max(entry.z / cz, 0.0)) as usize, nz - 2);
+
+        let boundary = |cell: usize, size: f32, o: f32, d: f32| {
+            if d > 0.0 {
+                ((cell + 1) as f32 * size - o) / d
+            } else if d < 0.0 {
+                (cell as f32 * size - o) / d
+            } else {
+                f32::INFINITY
+            }
+        };
+
+        let mut t_next_x = boundary(x, cx, o.x, r.dir.x);
+        let mut t_next_z = boundary(z, cz, o.z, r.dir.z);
+        let t_delta_x = cx / r.dir.x.abs();
+        let t_delta_z = cz / r.dir.z.abs();
+        let mut t_cell = t_enter;
+
+        loop {
+            let t_leave = f32::min(f32::min(t_next_x, t_next_z), t_exit);
+
+            // Only build the triangles if the ray passes through the
+            // range of heights covered by the cell
+            let idx = z * nx + x;
+            let corners = [idx, idx + 1, idx + nx, idx + nx + 1];
+            let (low, high) =
+                corners.iter().fold((f32::MAX, f32::MIN), |(lo, hi), &i| {
+                    (
+                        f32::min(lo, self.heights[i]),
+                        f32::max(hi, self.heights[i]),
+                    )
+                });
+
+            let y0 = r.origin.y + t_cell * r.dir.y;
+            let y1 = r.origin.y + t_leave * r.dir.y;
+            if f32::max(y0, y1) >= low - BBOX_PADDING
+                && f32::min(y0, y1) <= high + BBOX_PADDING
+            {
+                if let Some(hit) = self.intersect_cell(x, z, r, t_min, t_max) {
+                    return Some(hit);
+                }
+            }
+
+            if t_leave >= t_exit {
+                return None;
+            }
+
+            t_cell = t_leave;
+            if t_next_x < t_next_z {
+                if r.dir.x > 0.0 && x + 2 < nx {
+                    x += 1;
+                } else if r.dir.x < 0.0 && x > 0 {
+                    x -= 1;
+                } else {
+                    return None;
+                }
+                t_next_x += t_delta_x;
+            } else {
+                if r.dir.z > 0.0 && z + 2 < nz {
+                    z += 1;
+                } else if r.dir.z < 0.0 && z > 0 {
+                    z -= 1;
+                } else {
+                    return None;
+                }
+                t_next_z += t_delta_z;
+            }
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Intersects the two triangles of the cell starting at sample (x, z)
+    fn intersect_cell(
+        &self,
+        x: usize,
+        z: usize,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let nx = self.resolution.0;
+        let idx = z * nx + x;
+        let vertex = |i: usize, dx: usize, dz: usize| {
+            Vector::new(
+                self.corner.x + (x + dx) as f32 * self.cell_size.0,
+                self.heights[i],
+                self.corner.z + (z + dz) as f32 * self.cell_size.1,
+            )
+        };
+
+        let (i00, i10, i01, i11) = (idx, idx + 1, idx + nx, idx + nx + 1);
+        let p00 = vertex(i00, 0, 0);
+        let p10 = vertex(i10, 1, 0);
+        let p01 = vertex(i01, 0, 1);
+        let p11 = vertex(i11, 1, 1);
+
+        // Both triangles share the diagonal from (x, z) to (x + 1, z + 1)
+        let mut result = None;
+        let mut closest_so_far = t_max;
+        for &(a, b, pa, pb) in &[(i11, i10, p11, p10), (i01, i11, p01, p11)] {
+            if let Some((t, u, v)) = moller_trumbore(
+                p00,
+                pa - p00,
+                pb - p00,
+                r,
+                t_min,
+                closest_so_far,
+            ) {
+                let w = 1.0 - u - v;
+                let normal = (w * self.normals[i00]
+                    + u * self.normals[a]
+                    + v * self.normals[b])
+                    .normalize();
+                let p = r.point_at_parameter(t);
+                let (nx, nz) = self.resolution;
+
+                result = Some(Intersection {
+                    u: (p.x - self.corner.x)
+                        / ((nx - 1) as f32 * self.cell_size.0),
+                    v: (p.z - self.corner.z)
+                        / ((nz - 1) as f32 * self.cell_size.1),
+                    t,
+                    p,
+                    normal,
+                    tangent: None,
+                });
+                closest_so_far = t;
+            }
+        }
+
+        result
+    }
+}
+
+/// Fractal sum of value noise octaves, scaled to [0, 1]
+fn fbm(x: f32, z: f32, octaves: u32, seed: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    let mut frequency = 1.0;
+
+    for octave in 0..octaves {
+        sum += amplitude
+            * value_noise(
+                x * frequency,
+                z * frequency,
+                seed.wrapping_add(octave),
+            );
+        total += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+
+    if total > 0.0 {
+        sum / total
+    } else {
+        0.0
+    }
+}
+
+/// Smoothly interpolates random values placed on the integer lattice
+fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (fx, fz) = (x - x0, z - z0);
+    let (sx, sz) = (fx * fx * (3.0 - 2.0 * fx), fz * fz * (3.0 - 2.0 * fz));
+
+    let (ix, iz) = (x0 as i32 as u32, z0 as i32 as u32);
+    let lattice = |dx: u32, dz: u32| {
+        let mut h = ix.wrapping_add(dx).wrapping_mul(0x27d4_eb2d)
+            ^ iz.wrapping_add(dz).wrapping_mul(0x1656_67b1)
+            ^ seed.wrapping_mul(0x9e37_79b9);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x2c1b_3c6d);
+        h ^= h >> 12;
+        h = h.wrapping_mul(0x297a_2d39);
+        h ^= h >> 15;
+        h as f32 / u32::MAX as f32
+    };
+
+    let top = lattice(0, 0) + sx * (lattice(1, 0) - lattice(0, 0));
+    let bottom = lattice(0, 1) + sx * (lattice(1, 1) - lattice(0, 1));
+    top + sz * (bottom - top)
+}
@@ -0,0 +1,444 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{triangle::moller_trumbore, Intersection};
+use crate::{aabb::Aabb, ray::Ray, Vector};
+
+use std::cmp::Ordering;
+
+/// Maximum number of faces stored in a single leaf of the face hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// Number of node indices the traversal can hold at once. Each level of
+/// the hierarchy adds at most one entry, and median splits keep the depth
+/// of a mesh with `u32::MAX` faces at around 30 levels.
+const STACK_SIZE: usize = 64;
+
+/// Axis-aligned faces would otherwise get a zero-thickness bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A face of a [`Mesh`]. Each corner refers to an entry in the shared
+/// vertex buffer, and optionally to entries in the normal and texture
+/// coordinate buffers.
+///
+/// [`Mesh`]: struct.Mesh.html
+#[derive(Debug, Copy, Clone)]
+pub struct MeshFace {
+    pub vertices: [u32; 3],
+    pub normals: Option<[u32; 3]>,
+    pub uvs: Option<[u32; 3]>,
+}
+
+/// A node in the flattened face hierarchy. Interior nodes have a `count`
+/// of zero, their left child directly follows them and `offset` is the
+/// index of their right child. Leaf nodes cover the faces
+/// `offset..offset + count`.
+#[derive(Debug, Copy, Clone)]
+struct MeshNode {
+    bbox: Aabb,
+    offset: u32,
+    count: u32,
+}
+
+/// A triangle mesh that stores its vertices, normals and texture
+/// coordinates once and refers to them by index from each face. The
+/// faces are kept in their own hierarchy, so the whole mesh only takes
+/// up a single leaf (and a single material) in the scene [`Bvh`].
+///
+/// Building the hierarchy reorders the faces, so they are only reached by
+/// traversing it and there is no way to look one up by its index in the
+/// source file.
+///
+/// [`Bvh`]: ../bvh/struct.Bvh.html
+#[derive(Debug)]
+pub struct Mesh {
+    vertices: Vec<Vector>,
+    normals: Vec<Vector>,
+    uvs: Vec<(f32, f32)>,
+    faces: Vec<MeshFace>,
+    nodes: Vec<MeshNode>,
+}
+
+struct BuildItem {
+    face: MeshFace,
+    bbox: Aabb,
+    centroid: Vector,
+}
+
+impl Mesh {
+    pub fn new(
+        vertices: Vec<Vector>,
+        normals: Vec<Vector>,
+        uvs: Vec<(f32, f32)>,
+        faces: Vec<MeshFace>,
+    ) -> Self {
+        assert!(!faces.is_empty(), "mesh must have at least one face");
+
+        let mut items: Vec<BuildItem> = faces
+            .into_iter()
+            .map(|face| {
+                let [a, b, c] = face.vertices;
+                let (v0, v1, v2) = (
+                    vertices[a as usize],
+                    vertices[b as usize],
+                    vertices[c as usize],
+                );
+
+                let mut min = v0;
+                let mut max = v0;
+                for v in &[v1, v2] {
+                    for a in 0..3 {
+                        min[a] = f32::min(min[a], v[a]);
+                        max[a] = f32::max(max[a], v[a]);
+                    }
+                }
+
+                BuildItem {
+                    face,
+                    bbox: Aabb::new(min - BBOX_PADDING, max + BBOX_PADDING),
+                    centroid: (v0 + v1 + v2) / 3.0,
+                }
+            })
+            .collect();
+
+        let mut nodes = Vec::with_capacity(2 * items.len() / LEAF_SIZE + 1);
+        build(&mut items, 0, &mut nodes);
+
+        Self {
+            vertices,
+            normals,
+            uvs,
+            faces: items.into_iter().map(|i| i.face).collect(),
+            nodes,
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let mut result = None;
+        let mut closest_so_far = t_max;
+
+        let mut stack = [0_u32; STACK_SIZE];
+        let mut stack_len = 1;
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let idx = stack[stack_len] as usize;
+            let node = self.nodes[idx];
+
+            if !node.bbox.hit(r, t_min, closest_so_far) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack[stack_len] = node.offset;
+                stack[stack_len + 1] = idx as u32 + 1;
+                stack_len += 2;
+                continue;
+            }
+
+            let start = node.offset as usize;
+            for face in &self.faces[start..start + node.count as usize] {
+                if let Some(hit) =
+                    self.intersect_face(face, r, t_min, closest_so_far)
+                {
+                    result = Some(hit);
+                    closest_so_far = hit.t;
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.nodes[0].bbox
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    fn intersect_face(
+        &self,
+        face: &MeshFace,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let [a, b, c] = face.vertices;
+        let v0 = self.vertices[a as usize];
+        let edge1 = self.vertices[b as usize] - v0;
+        let edge2 = self.vertices[c as usize] - v0;
+
+        moller_trumbore(v0, edge1, edge2, r, t_min, t_max).map(|(t, u, v)| {
+            let w = 1.0 - u - v;
+
+            let normal = match face.normals {
+                Some([na, nb, nc]) => (w * self.normals[na as usize]
+                    + u * self.normals[nb as usize]
+                    + v * self.normals[nc as usize])
+                    .normalize(),
+                None => edge1.cross(edge2).normalize(),
+            };
+
+            let (u, v) = match face.uvs {
+                Some([ta, tb, tc]) => {
+                    let (ta, tb, tc) = (
+                        self.uvs[ta as usize],
+                        self.uvs[tb as usize],
+                        self.uvs[tc as usize],
+                    );
+                    (
+                        w * ta.0 + u * tb.0 + v * tc.0,
+                        w * ta.1 + u * tb.1 + v * tc.1,
+                    )
+                }
+                None => (u, v),
+            };
+
+            Intersection {
+                u,
+                v,
+                t,
+                p: r.point_at_parameter(t),
+                normal,
+                tangent: None,
+            }
+        })
+    }
+}
+
+/// Recursively builds the face hierarchy, reordering `items` so that
+/// every leaf covers a contiguous range of them
+fn build(items: &mut [BuildItem], offset: usize, nodes: &mut Vec<MeshNode>) {
+    let bbox = items[1..]
+        .iter()
+        .fold(items[0].bbox, |b, i| Aabb::surrounding_box(b, i.bbox));
+
+    let idx = nodes.len();
+    nodes.push(MeshNode {
+        bbox,
+        offset: offset as u32,
+        count: items.len() as u32,
+    });
+
+    if items.len() <= LEAF_SIZE {
+        return;
+    }
+
+    // Split at the median centroid along the axis the centroids are
+    // most spread out on
+    let mut min = items[0].centroid;
+    let mut max = items[0].centroid;
+    for item in &items[1..] {
+        for a in 0..3 {
+            min[a] = f32::min(min[a], item.centroid[a]);
+            max[a] = f32::max(max[a], item.centroid[a]);
+        }
+    }
+
+    let extent = max - min;
+    let axis = if extent.x > extent.y && extent.x > extent.z {
+        0
+    } else if extent.y > extent.z {
+        1
+    } else {
+        2
+    };
+
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| {
+        a.centroid[axis]
+            .partial_cmp(&b.centroid[axis])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let (left, right) = items.split_at_mut(mid);
+    build(left, offset, nodes);
+
+    let right_idx = nodes.len();
+    build(right, offset + mid, nodes);
+
+    nodes[idx].offset = right_idx as u32;
+    nodes[idx].count = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 0.0001;
+
+    /// A unit square in the XY plane facing +Z, split along its diagonal.
+    /// The texture coordinates match the vertex positions and the normals
+    /// lean outwards along X, so both vary across the faces.
+    fn quad() -> Mesh {
+        let vertices = vec![
+            Vector::new(0.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(1.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ];
+        let normals = vec![
+            Vector::new(-1.0, 0.0, 1.0),
+            Vector::new(1.0, 0.0, 1.0),
+            Vector::new(1.0, 0.0, 1.0),
+            Vector::new(-1.0, 0.0, 1.0),
+        ];
+        let uvs = vertices.iter().map(|v| (v.x, v.y)).collect();
+        let faces = [[0, 1, 2], [0, 2, 3]]
+            .iter()
+            .map(|&f| MeshFace {
+                vertices: f,
+                normals: Some(f),
+                uvs: Some(f),
+            })
+            .collect();
+
+        Mesh::new(vertices, normals, uvs, faces)
+    }
+
+    /// `count` copies of the unit square stacked one unit apart along Z
+    fn layers(count: usize) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+
+        for i in 0..count {
+            let z = i as f32;
+            let n = vertices.len() as u32;
+            vertices.extend_from_slice(&[
+                Vector::new(0.0, 0.0, z),
+                Vector::new(1.0, 0.0, z),
+                Vector::new(1.0, 1.0, z),
+                Vector::new(0.0, 1.0, z),
+            ]);
+            for f in &[[n, n + 1, n + 2], [n, n + 2, n + 3]] {
+                faces.push(MeshFace {
+                    vertices: *f,
+                    normals: None,
+                    uvs: None,
+                });
+            }
+        }
+
+        Mesh::new(vertices, Vec::new(), Vec::new(), faces)
+    }
+
+    fn ray(origin: Vector, dir: Vector) -> Ray {
+        Ray {
+            origin,
+            dir,
+            time: 0.0,
+        }
+    }
+
+    fn down_at(x: f32, y: f32) -> Ray {
+        ray(Vector::new(x, y, 5.0), Vector::new(0.0, 0.0, -1.0))
+    }
+
+    fn depth(mesh: &Mesh, idx: usize) -> usize {
+        let node = mesh.nodes[idx];
+        if node.count > 0 {
+            return 0;
+        }
+
+        1 + usize::max(depth(mesh, idx + 1), depth(mesh, node.offset as usize))
+    }
+
+    #[test]
+    fn hits_both_halves_of_a_quad() {
+        let mesh = quad();
+
+        for &(x, y) in &[(0.75, 0.25), (0.25, 0.75)] {
+            let hit = mesh
+                .intersect(down_at(x, y), 0.0, f32::MAX)
+                .unwrap_or_else(|| panic!("missed the quad at {}, {}", x, y));
+
+            assert!((hit.t - 5.0).abs() < EPSILON);
+            assert!((hit.p - Vector::new(x, y, 0.0)).length() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn misses_rays_passing_by() {
+        let mesh = quad();
+
+        assert!(mesh.intersect(down_at(1.5, 0.5), 0.0, f32::MAX).is_none());
+        assert!(mesh.intersect(down_at(0.5, -0.5), 0.0, f32::MAX).is_none());
+        assert!(mesh.intersect(down_at(0.5, 0.5), 0.0, 4.0).is_none());
+    }
+
+    #[test]
+    fn interpolates_normals_and_uvs() {
+        let mesh = quad();
+
+        // The X component of the blended normal is 2x - 1 before it is
+        // normalized, and the texture coordinates follow the position
+        for &(x, y) in &[(0.75, 0.25), (0.25, 0.75), (0.5, 0.1)] {
+            let hit = mesh.intersect(down_at(x, y), 0.0, f32::MAX).unwrap();
+            let expected = Vector::new(2.0 * x - 1.0, 0.0, 1.0).normalize();
+
+            assert!(hit.normal.dot(expected) > 0.99, "{:?}", hit.normal);
+            assert!((hit.u - x).abs() < EPSILON, "u = {}", hit.u);
+            assert!((hit.v - y).abs() < EPSILON, "v = {}", hit.v);
+        }
+    }
+
+    #[test]
+    fn closest_hit_wins_across_leaves() {
+        let count = 20;
+        let mesh = layers(count);
+        assert!(mesh.nodes.len() > 1);
+
+        let from_above =
+            ray(Vector::new(0.3, 0.6, 100.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = mesh.intersect(from_above, 0.0, f32::MAX).unwrap();
+        assert!((hit.t - (100.0 - (count - 1) as f32)).abs() < EPSILON);
+
+        let from_below =
+            ray(Vector::new(0.6, 0.3, -100.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(from_below, 0.0, f32::MAX).unwrap();
+        assert!((hit.t - 100.0).abs() < EPSILON);
+
+        // Starting between two layers only sees the one in front
+        let between =
+            ray(Vector::new(0.6, 0.3, 7.5), Vector::new(0.0, 0.0, -1.0));
+        let hit = mesh.intersect(between, 0.0, f32::MAX).unwrap();
+        assert!((hit.t - 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hierarchy_depth_fits_the_traversal_stack() {
+        // Median splits halve the faces at each level until a leaf holds
+        // at most LEAF_SIZE of them
+        let levels = |faces: f64| {
+            (faces / LEAF_SIZE as f64).log2().ceil().max(0.0) as usize
+        };
+
+        for &count in &[1, 2, 3, 10, 64, 500] {
+            let mesh = layers(count);
+            let faces = mesh.face_count();
+            assert!(depth(&mesh, 0) <= levels(faces as f64), "{}", faces);
+        }
+
+        // The traversal holds at most one entry per level plus the root
+        assert!(levels(u32::MAX as f64) < STACK_SIZE);
+    }
+}
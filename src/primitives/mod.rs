@@ -17,15 +17,65 @@ use std::fmt::Debug;
 
 use crate::{aabb::Aabb, ray::Ray, Vector};
 
+/// How far past a boundary the next one is searched for when walking
+/// along a ray
+const SPAN_EPSILON: f32 = 0.0001;
+
+/// Upper bound on the boundaries visited when walking along a ray, in
+/// case a primitive keeps reporting the same one
+const MAX_SPAN_BOUNDARIES: usize = 64;
+
 mod block;
 pub use block::*;
 
+mod cone;
+pub use cone::*;
+
+mod csg;
+pub use csg::*;
+
+mod curve;
+pub use curve::*;
+
+mod cylinder;
+pub use cylinder::*;
+
+mod disk;
+pub use disk::*;
+
+mod heightfield;
+pub use heightfield::*;
+
+mod mesh;
+pub use mesh::*;
+
+mod moving_sphere;
+pub use moving_sphere::*;
+
+mod plane;
+pub use plane::*;
+
+mod polygon;
+pub use polygon::*;
+
+mod quad;
+pub use quad::*;
+
 mod rectangle;
 pub use rectangle::*;
 
+mod sdf;
+pub use sdf::*;
+
 mod sphere;
 pub use sphere::*;
 
+mod torus;
+pub use torus::*;
+
+mod transformation;
+pub use transformation::*;
+
 mod triangle;
 pub use triangle::*;
 
@@ -39,6 +89,18 @@ pub struct Intersection {
     pub p: Vector,
     /// The intersection surface normal
     pub normal: Vector,
+    /// The direction along the surface for primitives that have one, such
+    /// as curves
+    pub tangent: Option<Vector>,
+}
+
+/// A point sampled uniformly over the surface of a primitive
+#[derive(Debug, Copy, Clone)]
+pub struct SurfaceSample {
+    pub p: Vector,
+    pub normal: Vector,
+    /// Probability density of the sample with respect to surface area
+    pub pdf: f32,
 }
 
 // pub trait Primitive: Debug + Send + Sync {
@@ -46,24 +108,24 @@ pub struct Intersection {
 //     fn bounding_box(&self) -> Option<Aabb>;
 // }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum RotationAxis {
-    X,
-    Y,
-    Z,
-}
-
-#[derive(Debug)]
-pub enum Transformation {
-    Rotate(f32, RotationAxis),
-    Translate(Vector),
-}
-
 #[derive(Debug)]
 pub enum Primitive {
     Block(Block),
+    Cone(Cone),
+    Csg(Csg),
+    Curve(Curve),
+    Cylinder(Cylinder),
+    Disk(Disk),
+    Heightfield(Heightfield),
+    Mesh(Mesh),
+    MovingSphere(MovingSphere),
+    Plane(Plane),
+    Polygon(Polygon),
+    Quad(Quad),
     Rectangle(Rectangle),
+    Sdf(Sdf),
     Sphere(Sphere),
+    Torus(Torus),
     Triangle(Triangle),
 }
 
@@ -76,8 +138,21 @@ impl Primitive {
     ) -> Option<Intersection> {
         match self {
             Primitive::Block(o) => o.intersect(r, t_min, t_max),
+            Primitive::Cone(o) => o.intersect(r, t_min, t_max),
+            Primitive::Csg(o) => o.intersect(r, t_min, t_max),
+            Primitive::Curve(o) => o.intersect(r, t_min, t_max),
+            Primitive::Cylinder(o) => o.intersect(r, t_min, t_max),
+            Primitive::Disk(o) => o.intersect(r, t_min, t_max),
+            Primitive::Heightfield(o) => o.intersect(r, t_min, t_max),
+            Primitive::Mesh(o) => o.intersect(r, t_min, t_max),
+            Primitive::MovingSphere(o) => o.intersect(r, t_min, t_max),
+            Primitive::Plane(o) => o.intersect(r, t_min, t_max),
+            Primitive::Polygon(o) => o.intersect(r, t_min, t_max),
+            Primitive::Quad(o) => o.intersect(r, t_min, t_max),
             Primitive::Rectangle(o) => o.intersect(r, t_min, t_max),
+            Primitive::Sdf(o) => o.intersect(r, t_min, t_max),
             Primitive::Sphere(o) => o.intersect(r, t_min, t_max),
+            Primitive::Torus(o) => o.intersect(r, t_min, t_max),
             Primitive::Triangle(o) => o.intersect(r, t_min, t_max),
         }
     }
@@ -85,9 +160,103 @@ impl Primitive {
     pub fn bounding_box(&self) -> Aabb {
         match self {
             Primitive::Block(o) => o.bounding_box(),
+            Primitive::Cone(o) => o.bounding_box(),
+            Primitive::Csg(o) => o.bounding_box(),
+            Primitive::Curve(o) => o.bounding_box(),
+            Primitive::Cylinder(o) => o.bounding_box(),
+            Primitive::Disk(o) => o.bounding_box(),
+            Primitive::Heightfield(o) => o.bounding_box(),
+            Primitive::Mesh(o) => o.bounding_box(),
+            Primitive::MovingSphere(o) => o.bounding_box(),
+            Primitive::Plane(o) => o.bounding_box(),
+            Primitive::Polygon(o) => o.bounding_box(),
+            Primitive::Quad(o) => o.bounding_box(),
             Primitive::Rectangle(o) => o.bounding_box(),
+            Primitive::Sdf(o) => o.bounding_box(),
             Primitive::Sphere(o) => o.bounding_box(),
+            Primitive::Torus(o) => o.bounding_box(),
             Primitive::Triangle(o) => o.bounding_box(),
         }
     }
+
+    /// Whether the primitive fits in a finite bounding box, and can be
+    /// placed in a [`Bvh`]
+    ///
+    /// [`Bvh`]: ../bvh/struct.Bvh.html
+    pub fn is_bounded(&self) -> bool {
+        !matches!(self, Primitive::Plane(_))
+    }
+
+    /// Surface area of primitives that can be used as area lights
+    pub fn area(&self) -> Option<f32> {
+        match self {
+            Primitive::Polygon(o) => Some(o.area()),
+            Primitive::Quad(o) => Some(o.area()),
+            Primitive::Rectangle(o) => Some(o.area()),
+            _ => None,
+        }
+    }
+
+    /// Picks a uniformly distributed point on primitives that can be
+    /// used as area lights
+    pub fn sample(&self) -> Option<SurfaceSample> {
+        match self {
+            Primitive::Polygon(o) => Some(o.sample()),
+            Primitive::Quad(o) => Some(o.sample()),
+            Primitive::Rectangle(o) => Some(o.sample()),
+            _ => None,
+        }
+    }
+
+    /// Finds every stretch of the ray between `t_min` and `t_max` that
+    /// lies inside the primitive. Only meaningful for closed primitives
+    /// with outward facing normals.
+    pub fn spans(&self, r: Ray, t_min: f32, t_max: f32) -> Vec<Span> {
+        if let Primitive::Csg(o) = self {
+            return o.spans(r, t_min, t_max);
+        }
+
+        // Walk along the ray from boundary to boundary, using the
+        // normals to tell whether each one is an entry or an exit
+        let mut spans = Vec::new();
+        let mut enter: Option<Intersection> = None;
+        let mut t = t_min;
+
+        for _ in 0..MAX_SPAN_BOUNDARIES {
+            let hit = match self.intersect(r, t, t_max) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            if hit.normal.dot(r.dir) < 0.0 {
+                enter = enter.or(Some(hit));
+            } else {
+                // An exit with no entry means the ray started inside
+                let e = enter.take().unwrap_or(Intersection {
+                    t: t_min,
+                    p: r.point_at_parameter(t_min),
+                    ..hit
+                });
+                spans.push(Span {
+                    enter: e,
+                    exit: hit,
+                });
+            }
+
+            t = hit.t + SPAN_EPSILON;
+        }
+
+        if let Some(e) = enter {
+            spans.push(Span {
+                enter: e,
+                exit: Intersection {
+                    t: t_max,
+                    p: r.point_at_parameter(t_max),
+                    ..e
+                },
+            });
+        }
+
+        spans
+    }
 }
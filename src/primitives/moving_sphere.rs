@@ -0,0 +1,88 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{Intersection, Sphere};
+use crate::{aabb::Aabb, ray::Ray, Vector};
+
+/// A sphere whose center moves in a straight line from `center0` at
+/// `time0` to `center1` at `time1`
+#[derive(Debug)]
+pub struct MovingSphere {
+    /// The sphere as it is at `time0`
+    sphere: Sphere,
+    center0: Vector,
+    center1: Vector,
+    time0: f32,
+    time1: f32,
+    bbox: Aabb,
+}
+
+impl MovingSphere {
+    pub fn new(
+        center0: Vector,
+        center1: Vector,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+    ) -> Self {
+        let sphere = Sphere::new(center0, radius);
+        let extent = Vector::new(radius, radius, radius);
+        let bbox = Aabb::surrounding_box(
+            sphere.bounding_box(),
+            Aabb::new(center1 - extent, center1 + extent),
+        );
+
+        Self {
+            sphere,
+            center0,
+            center1,
+            time0,
+            time1,
+            bbox,
+        }
+    }
+
+    pub fn center(&self, time: f32) -> Vector {
+        if self.time1 <= self.time0 {
+            return self.center0;
+        }
+
+        let s = (time - self.time0) / (self.time1 - self.time0);
+        self.center0 + s.clamp(0.0, 1.0) * (self.center1 - self.center0)
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Move the ray instead of the sphere
+        let offset = self.center(r.time) - self.center0;
+        let local = Ray {
+            origin: r.origin - offset,
+            ..r
+        };
+
+        self.sphere.intersect(local, t_min, t_max).map(|mut i| {
+            i.p += offset;
+            i
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
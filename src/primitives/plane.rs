@@ -0,0 +1,78 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::Intersection;
+use crate::{aabb::Aabb, math::Onb, ray::Ray, Vector};
+
+/// An infinite plane through `point`, facing in the direction of its
+/// normal. Planes have no finite bounds, so the [`Scene`] keeps them out
+/// of its [`Bvh`] and tests them separately.
+///
+/// [`Scene`]: ../config/struct.Scene.html
+/// [`Bvh`]: ../bvh/struct.Bvh.html
+#[derive(Debug)]
+pub struct Plane {
+    point: Vector,
+    basis: Onb,
+    /// Distance over which the texture coordinates repeat
+    uv_scale: f32,
+}
+
+impl Plane {
+    pub fn new(point: Vector, normal: Vector, uv_scale: f32) -> Self {
+        Self {
+            point,
+            basis: Onb::build_from_w(normal),
+            uv_scale,
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let normal = self.basis.w();
+        let t = (self.point - r.origin).dot(normal) / r.dir.dot(normal);
+        if t < t_min || t > t_max || t.is_nan() {
+            return None;
+        }
+
+        let p = r.point_at_parameter(t);
+        let offset = p - self.point;
+
+        Some(Intersection {
+            u: (offset.dot(self.basis.u()) / self.uv_scale).rem_euclid(1.0),
+            v: (offset.dot(self.basis.v()) / self.uv_scale).rem_euclid(1.0),
+            t,
+            p,
+            normal,
+            tangent: None,
+        })
+    }
+
+    /// Planes extend forever, so this box covers all of space
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::new(
+            Vector::new(
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+            ),
+            Vector::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        )
+    }
+}
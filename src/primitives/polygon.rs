@@ -0,0 +1,248 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{Intersection, SurfaceSample};
+use crate::{aabb::Aabb, math::Onb, ray::Ray, Vector};
+
+use rand::prelude::*;
+
+/// Keeps axis-aligned polygons from getting a zero-thickness bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A flat polygon with any number of vertices. The polygon may be concave
+/// but must not intersect itself. The normal follows the right hand rule
+/// with respect to the vertex order.
+#[derive(Debug)]
+pub struct Polygon {
+    origin: Vector,
+    normal: Vector,
+    basis: Onb,
+    /// Vertices projected into the plane of the polygon
+    points: Vec<(f32, f32)>,
+    /// Minimum and size of the projected bounding rectangle, used to
+    /// compute the texture coordinates
+    uv_min: (f32, f32),
+    uv_size: (f32, f32),
+    /// Triangulation of the polygon, used for sampling
+    triangles: Vec<[Vector; 3]>,
+    /// Cumulative areas of the triangles
+    cdf: Vec<f32>,
+    area: f32,
+    bbox: Aabb,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Vector>) -> Self {
+        assert!(vertices.len() >= 3, "polygon needs at least 3 vertices");
+
+        // Newell's method gives a robust normal for any simple polygon,
+        // and its length is twice the area
+        let mut n = Vector::zeros();
+        for (i, a) in vertices.iter().enumerate() {
+            let b = vertices[(i + 1) % vertices.len()];
+            n.x += (a.y - b.y) * (a.z + b.z);
+            n.y += (a.z - b.z) * (a.x + b.x);
+            n.z += (a.x - b.x) * (a.y + b.y);
+        }
+
+        let origin = vertices[0];
+        let normal = n.normalize();
+        let basis = Onb::build_from_w(normal);
+
+        let points: Vec<(f32, f32)> = vertices
+            .iter()
+            .map(|&v| {
+                let local = basis.to_local(v - origin);
+                (local.x, local.y)
+            })
+            .collect();
+
+        let mut uv_min = points[0];
+        let mut uv_max = points[0];
+        for &(x, y) in &points[1..] {
+            uv_min = (f32::min(uv_min.0, x), f32::min(uv_min.1, y));
+            uv_max = (f32::max(uv_max.0, x), f32::max(uv_max.1, y));
+        }
+
+        let mut min = origin;
+        let mut max = origin;
+        for v in &vertices[1..] {
+            for a in 0..3 {
+                min[a] = f32::min(min[a], v[a]);
+                max[a] = f32::max(max[a], v[a]);
+            }
+        }
+
+        let triangles: Vec<[Vector; 3]> = triangulate(&points)
+            .into_iter()
+            .map(|[a, b, c]| [vertices[a], vertices[b], vertices[c]])
+            .collect();
+
+        let mut cdf = Vec::with_capacity(triangles.len());
+        let mut total = 0.0;
+        for [a, b, c] in &triangles {
+            total += (*b - *a).cross(*c - *a).length() / 2.0;
+            cdf.push(total);
+        }
+
+        Self {
+            origin,
+            normal,
+            basis,
+            points,
+            uv_min,
+            uv_size: (uv_max.0 - uv_min.0, uv_max.1 - uv_min.1),
+            triangles,
+            cdf,
+            area: n.length() / 2.0,
+            bbox: Aabb::new(min - BBOX_PADDING, max + BBOX_PADDING),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let denom = self.normal.dot(r.dir);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.origin - r.origin) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.point_at_parameter(t);
+        let local = self.basis.to_local(p - self.origin);
+        let (x, y) = (local.x, local.y);
+
+        // Even-odd crossing test, which also handles concave polygons
+        let mut inside = false;
+        let mut j = self.points.len() - 1;
+        for (i, &(xi, yi)) in self.points.iter().enumerate() {
+            let (xj, yj) = self.points[j];
+            if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+
+        if !inside {
+            return None;
+        }
+
+        Some(Intersection {
+            u: (x - self.uv_min.0) / self.uv_size.0,
+            v: (y - self.uv_min.1) / self.uv_size.1,
+            t,
+            p,
+            normal: self.normal,
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    pub fn area(&self) -> f32 {
+        self.area
+    }
+
+    /// Picks a uniformly distributed point on the polygon
+    pub fn sample(&self) -> SurfaceSample {
+        let mut rng = rand::thread_rng();
+
+        // Choose a triangle proportional to its area, then a point
+        // uniformly within it
+        let target = rng.gen::<f32>() * self.cdf[self.cdf.len() - 1];
+        let idx = self
+            .cdf
+            .iter()
+            .position(|&c| c >= target)
+            .unwrap_or(self.cdf.len() - 1);
+        let [a, b, c] = self.triangles[idx];
+
+        let s = rng.gen::<f32>().sqrt();
+        let t = rng.gen::<f32>();
+
+        SurfaceSample {
+            p: a * (1.0 - s) + b * (s * (1.0 - t)) + c * (s * t),
+            normal: self.normal,
+            pdf: 1.0 / self.area,
+        }
+    }
+}
+
+/// Splits a simple polygon into triangles by ear clipping. Returns
+/// triples of indices into `points`.
+fn triangulate(points: &[(f32, f32)]) -> Vec<[usize; 3]> {
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    // Ears are the convex corners, so work out which way the
+    // polygon winds first
+    let signed_area: f32 = (0..points.len())
+        .map(|i| cross((0.0, 0.0), points[i], points[(i + 1) % points.len()]))
+        .sum();
+    let winding = signed_area.signum();
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                points[remaining[(i + n - 1) % n]],
+                points[remaining[i]],
+                points[remaining[(i + 1) % n]],
+            );
+
+            if cross(a, b, c) * winding <= 0.0 {
+                return false;
+            }
+
+            // No other vertex may lie inside the ear
+            remaining.iter().all(|&j| {
+                let p = points[j];
+                p == a
+                    || p == b
+                    || p == c
+                    || cross(a, b, p) * winding < 0.0
+                    || cross(b, c, p) * winding < 0.0
+                    || cross(c, a, p) * winding < 0.0
+            })
+        });
+
+        // Only degenerate input has no ears left, in which case the
+        // rest is clipped off as a fan
+        let i = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ]);
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
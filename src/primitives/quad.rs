@@ -0,0 +1,120 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{Intersection, SurfaceSample};
+use crate::{aabb::Aabb, ray::Ray, Vector};
+
+use rand::prelude::*;
+
+/// Keeps axis-aligned quads from getting a zero-thickness bounding box
+const BBOX_PADDING: f32 = 0.0001;
+
+/// A parallelogram with one corner at `corner`, spanned by the two edge
+/// vectors. Unlike [`Rectangle`] it can face in any direction. The normal
+/// points along `edge_u × edge_v`.
+///
+/// [`Rectangle`]: struct.Rectangle.html
+#[derive(Debug)]
+pub struct Quad {
+    corner: Vector,
+    edge_u: Vector,
+    edge_v: Vector,
+    normal: Vector,
+    /// Cached `n / (n · n)`, used to find the planar coordinates of a hit
+    w: Vector,
+    area: f32,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(corner: Vector, edge_u: Vector, edge_v: Vector) -> Self {
+        let n = edge_u.cross(edge_v);
+
+        let mut min = corner;
+        let mut max = corner;
+        for p in &[corner + edge_u, corner + edge_v, corner + edge_u + edge_v] {
+            for a in 0..3 {
+                min[a] = f32::min(min[a], p[a]);
+                max[a] = f32::max(max[a], p[a]);
+            }
+        }
+
+        Self {
+            corner,
+            edge_u,
+            edge_v,
+            normal: n.normalize(),
+            w: n / n.dot(n),
+            area: n.length(),
+            bbox: Aabb::new(min - BBOX_PADDING, max + BBOX_PADDING),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let denom = self.normal.dot(r.dir);
+        if denom == 0.0 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.corner - r.origin) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.point_at_parameter(t);
+        let h = p - self.corner;
+        let alpha = self.w.dot(h.cross(self.edge_v));
+        let beta = self.w.dot(self.edge_u.cross(h));
+
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some(Intersection {
+            u: alpha,
+            v: beta,
+            t,
+            p,
+            normal: self.normal,
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    pub fn area(&self) -> f32 {
+        self.area
+    }
+
+    /// Picks a uniformly distributed point on the quad
+    pub fn sample(&self) -> SurfaceSample {
+        let mut rng = rand::thread_rng();
+
+        SurfaceSample {
+            p: self.corner
+                + self.edge_u * rng.gen::<f32>()
+                + self.edge_v * rng.gen::<f32>(),
+            normal: self.normal,
+            pdf: 1.0 / self.area,
+        }
+    }
+}
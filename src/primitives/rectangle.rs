@@ -13,7 +13,9 @@
  * You should have received a copy of the GNU General Public License
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
-use super::Intersection;
+use rand::prelude::*;
+
+use super::{Intersection, SurfaceSample};
 use crate::{aabb::Aabb, ray::Ray, Vector};
 
 #[derive(PartialEq, Eq)]
@@ -104,10 +106,32 @@ impl Rectangle {
             t,
             p: r.point_at_parameter(t),
             normal: self.norm,
+            tangent: None,
         })
     }
 
     pub fn bounding_box(&self) -> Aabb {
         self.bbox
     }
+
+    pub fn area(&self) -> f32 {
+        (self.a1 - self.a0) * (self.b1 - self.b0)
+    }
+
+    /// Picks a uniformly distributed point on the rectangle
+    pub fn sample(&self) -> SurfaceSample {
+        let mut rng = rand::thread_rng();
+        let (k_ax, a_ax, b_ax) = self.plane;
+
+        let mut p = Vector::zeros();
+        p[k_ax] = self.k;
+        p[a_ax] = self.a0 + (self.a1 - self.a0) * rng.gen::<f32>();
+        p[b_ax] = self.b0 + (self.b1 - self.b0) * rng.gen::<f32>();
+
+        SurfaceSample {
+            p,
+            normal: self.norm,
+            pdf: 1.0 / self.area(),
+        }
+    }
 }
@@ -0,0 +1,257 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{sphere::sphere_uv, Intersection};
+use crate::{aabb::Aabb, ray::Ray, Vector};
+
+/// Building blocks for signed distance fields. Distances are negative
+/// inside a shape and positive outside of it.
+#[derive(Debug, Clone)]
+pub enum SdfShape {
+    Sphere {
+        center: Vector,
+        radius: f32,
+    },
+    Box {
+        center: Vector,
+        half_extents: Vector,
+    },
+    /// A box whose edges are rounded off with the given radius. The
+    /// radius is taken out of the half extents, so the outer size
+    /// matches a plain box.
+    RoundBox {
+        center: Vector,
+        half_extents: Vector,
+        radius: f32,
+    },
+    /// A torus lying flat in the XZ plane
+    Torus {
+        center: Vector,
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    /// The Mandelbulb fractal, which fits in a sphere of radius `scale`
+    /// around `center`
+    Mandelbulb {
+        center: Vector,
+        scale: f32,
+        power: f32,
+        iterations: usize,
+    },
+    Union(Box<SdfShape>, Box<SdfShape>),
+    Intersection(Box<SdfShape>, Box<SdfShape>),
+    /// The first shape with the second one cut out of it
+    Subtract(Box<SdfShape>, Box<SdfShape>),
+    /// Union that blends the shapes together over the given distance
+    SmoothUnion(Box<SdfShape>, Box<SdfShape>, f32),
+    /// Subtraction that blends the cut over the given distance
+    SmoothSubtract(Box<SdfShape>, Box<SdfShape>, f32),
+}
+
+impl SdfShape {
+    pub fn distance(&self, p: Vector) -> f32 {
+        match self {
+            Self::Sphere { center, radius } => (p - *center).length() - radius,
+            Self::Box {
+                center,
+                half_extents,
+            } => box_distance(p - *center, *half_extents),
+            Self::RoundBox {
+                center,
+                half_extents,
+                radius,
+            } => box_distance(p - *center, *half_extents - *radius) - radius,
+            Self::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let p = p - *center;
+                let ring = f32::sqrt(p.x * p.x + p.z * p.z) - major_radius;
+                f32::sqrt(ring * ring + p.y * p.y) - minor_radius
+            }
+            Self::Mandelbulb {
+                center,
+                scale,
+                power,
+                iterations,
+            } => {
+                mandelbulb_distance((p - *center) / *scale, *power, *iterations)
+                    * scale
+            }
+            Self::Union(a, b) => f32::min(a.distance(p), b.distance(p)),
+            Self::Intersection(a, b) => f32::max(a.distance(p), b.distance(p)),
+            Self::Subtract(a, b) => f32::max(a.distance(p), -b.distance(p)),
+            Self::SmoothUnion(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                lerp(d2, d1, h) - k * h * (1.0 - h)
+            }
+            Self::SmoothSubtract(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+                lerp(d1, -d2, h) + k * h * (1.0 - h)
+            }
+        }
+    }
+}
+
+/// A primitive defined by a signed distance field and rendered by sphere
+/// tracing. The field is only evaluated inside the supplied bounding box,
+/// which must enclose the whole shape.
+#[derive(Debug)]
+pub struct Sdf {
+    shape: SdfShape,
+    bbox: Aabb,
+    /// Maximum number of steps taken along a single ray
+    max_steps: usize,
+    /// Distance from the surface at which a ray counts as a hit
+    epsilon: f32,
+}
+
+impl Sdf {
+    pub fn new(
+        shape: SdfShape,
+        bbox: Aabb,
+        max_steps: usize,
+        epsilon: f32,
+    ) -> Self {
+        Self {
+            shape,
+            bbox,
+            max_steps,
+            epsilon,
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        let (mut t, t_end) = self.bbox.clip(r, t_min, t_max)?;
+        let dir_len = r.dir.length();
+
+        // Rays that start inside the shape (refracted ones, for instance)
+        // march towards the surface from the other side. Rays leaving a
+        // surface start right on it, so there the direction decides.
+        let start = self.shape.distance(r.point_at_parameter(t));
+        let mut on_surface = start.abs() < self.epsilon;
+        let side = if on_surface {
+            self.normal(r.point_at_parameter(t)).dot(r.dir).signum()
+        } else {
+            start.signum()
+        };
+
+        for _ in 0..self.max_steps {
+            let p = r.point_at_parameter(t);
+            let dist = side * self.shape.distance(p);
+
+            if dist < self.epsilon && !on_surface {
+                // Fractal distance estimators have a kink at the surface,
+                // so the gradient is taken slightly in front of the hit
+                let normal = self.normal(
+                    r.point_at_parameter(t - 2.0 * self.epsilon / dir_len),
+                );
+                let (u, v) = sphere_uv(normal);
+                return Some(Intersection {
+                    u,
+                    v,
+                    t,
+                    p,
+                    normal,
+                    tangent: None,
+                });
+            }
+
+            on_surface &= dist < self.epsilon;
+            t += f32::max(dist, self.epsilon) / dir_len;
+            if t > t_end {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Estimates the surface normal from the gradient of the field
+    fn normal(&self, p: Vector) -> Vector {
+        let h = self.epsilon;
+        let d = |offset: Vector| {
+            self.shape.distance(p + offset) - self.shape.distance(p - offset)
+        };
+
+        Vector::new(
+            d(Vector::new(h, 0.0, 0.0)),
+            d(Vector::new(0.0, h, 0.0)),
+            d(Vector::new(0.0, 0.0, h)),
+        )
+        .normalize()
+    }
+}
+
+fn box_distance(p: Vector, half_extents: Vector) -> f32 {
+    let q = Vector::new(
+        p.x.abs() - half_extents.x,
+        p.y.abs() - half_extents.y,
+        p.z.abs() - half_extents.z,
+    );
+
+    let outside =
+        Vector::new(f32::max(q.x, 0.0), f32::max(q.y, 0.0), f32::max(q.z, 0.0));
+    let inside = f32::min(f32::max(q.x, f32::max(q.y, q.z)), 0.0);
+
+    outside.length() + inside
+}
+
+/// Distance estimator for the Mandelbulb, using the running derivative
+/// of the iterated function
+fn mandelbulb_distance(p: Vector, power: f32, iterations: usize) -> f32 {
+    let mut z = p;
+    let mut dr = 1.0;
+    let mut r = 0.0;
+
+    for _ in 0..iterations {
+        r = z.length();
+        // A bailout well past 2 keeps the estimate continuous enough
+        // to march on
+        if r > 8.0 {
+            break;
+        }
+
+        let theta = f32::acos(z.z / r) * power;
+        let phi = f32::atan2(z.y, z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+
+        z = r.powf(power)
+            * Vector::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+            + p;
+    }
+
+    0.5 * r.ln() * r / dr
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
@@ -66,6 +66,7 @@ impl Sphere {
                     t: q_eq,
                     p: point_at_parameter,
                     normal: (point_at_parameter - self.center) / self.radius,
+                    tangent: None,
                 });
             }
         }
@@ -79,7 +80,7 @@ impl Sphere {
 }
 
 /// Computes the u and v values for a sphere
-fn sphere_uv(p: Vector) -> (f32, f32) {
+pub(super) fn sphere_uv(p: Vector) -> (f32, f32) {
     let phi = f32::atan2(p.z, p.x);
     let theta = f32::asin(p.y);
     ((1.0 - (phi + PI) / (2.0 * PI)), ((theta + PI / 2.0) / PI))
@@ -0,0 +1,129 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{disk::azimuth, disk::disk_extent, Intersection};
+use crate::{
+    aabb::Aabb,
+    math::{solve_quartic, Onb},
+    ray::Ray,
+    Vector,
+};
+
+use std::cmp::Ordering;
+
+/// A ring-shaped torus around `center`. The `major_radius` is the distance
+/// from the center to the middle of the tube, and the `minor_radius` is
+/// the radius of the tube itself.
+#[derive(Debug)]
+pub struct Torus {
+    center: Vector,
+    major_radius: f32,
+    minor_radius: f32,
+    basis: Onb,
+    bbox: Aabb,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vector,
+        axis: Vector,
+        major_radius: f32,
+        minor_radius: f32,
+    ) -> Self {
+        let basis = Onb::build_from_w(axis);
+        let extent = disk_extent(basis.w(), major_radius) + minor_radius;
+
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            basis,
+            bbox: Aabb::new(center - extent, center + extent),
+        }
+    }
+
+    pub fn intersect(
+        &self,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        // Work in a frame where the ring lies in the xy plane around the
+        // origin, with a unit length direction so the quartic stays
+        // reasonably well conditioned
+        let dir_len = r.dir.length();
+        let d = self.basis.to_local(r.dir) / dir_len;
+        let o = self.basis.to_local(r.origin - self.center);
+
+        // Solving from the point on the ray closest to the center keeps
+        // the coefficients small for rays starting far away
+        let shift = -o.dot(d);
+        let o = o + d * shift;
+
+        let outer = self.major_radius + self.minor_radius;
+        if o.length_squared() > outer * outer {
+            return None;
+        }
+
+        let (ox, oy, oz) = (o.x as f64, o.y as f64, o.z as f64);
+        let (dx, dy, dz) = (d.x as f64, d.y as f64, d.z as f64);
+        let major_sq = (self.major_radius as f64).powi(2);
+        let minor_sq = (self.minor_radius as f64).powi(2);
+
+        let n = ox * dx + oy * dy + oz * dz;
+        let q = ox * ox + oy * oy + oz * oz + major_sq - minor_sq;
+
+        let (roots, count) = solve_quartic([
+            q * q - 4.0 * major_sq * (ox * ox + oy * oy),
+            4.0 * n * q - 8.0 * major_sq * (ox * dx + oy * dy),
+            4.0 * n * n + 2.0 * q - 4.0 * major_sq * (dx * dx + dy * dy),
+            4.0 * n,
+            1.0,
+        ]);
+
+        let s = roots[..count]
+            .iter()
+            .map(|&s| s as f32)
+            .filter(|&s| {
+                let t = (s + shift) / dir_len;
+                t > t_min && t < t_max
+            })
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+
+        let t = (s + shift) / dir_len;
+        let local = o + d * s;
+        let rho = f32::sqrt(local.x * local.x + local.y * local.y);
+
+        // Direction from the middle of the tube to the hit point
+        let ring = Vector::new(
+            local.x - self.major_radius * local.x / rho,
+            local.y - self.major_radius * local.y / rho,
+            local.z,
+        );
+
+        Some(Intersection {
+            u: azimuth(local.x, local.y),
+            v: azimuth(rho - self.major_radius, local.z),
+            t,
+            p: r.point_at_parameter(t),
+            normal: self.basis.local(ring.normalize()),
+            tangent: None,
+        })
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,223 @@
+/**
+ * Copyright © 2019 Jayden Chan. All rights reserved.
+ *
+ * Nit is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License version 3
+ * as published by the Free Software Foundation.
+ *
+ * Nit is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with Nit. If not, see <https://www.gnu.org/licenses/>.
+ */
+use super::{Intersection, Primitive};
+use crate::{aabb::Aabb, keyframes::Keyframes, ray::Ray, Vector};
+
+use std::f32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RotationAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl RotationAxis {
+    /// The two axes spanning the plane of rotation, in the order that
+    /// makes the rotation right-handed
+    fn plane(&self) -> (usize, usize) {
+        match self {
+            RotationAxis::X => (1, 2),
+            RotationAxis::Y => (2, 0),
+            RotationAxis::Z => (0, 1),
+        }
+    }
+}
+
+/// Moves an object over time, which blurs it across the camera shutter
+/// interval
+#[derive(Debug)]
+pub enum Motion {
+    /// Slides from the offset `from` at `time0` to the offset `to` at
+    /// `time1`
+    Linear {
+        from: Vector,
+        to: Vector,
+        time0: f32,
+        time1: f32,
+    },
+    /// Follows a path through keyframed offsets
+    Keyframed(Keyframes<Vector>),
+}
+
+impl Motion {
+    pub fn offset(&self, time: f32) -> Vector {
+        match self {
+            Motion::Linear {
+                from,
+                to,
+                time0,
+                time1,
+            } => lerp_offset(time, (*time0, *from), (*time1, *to)),
+            Motion::Keyframed(keys) => keys.at(time),
+        }
+    }
+
+    /// The offsets the path turns at. Since it runs in straight lines
+    /// between them, they also bound the whole path.
+    fn corners(&self) -> Vec<Vector> {
+        match self {
+            Motion::Linear { from, to, .. } => vec![*from, *to],
+            Motion::Keyframed(keys) => keys.values().copied().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Transformation {
+    /// Rotates the object by an angle in degrees around an axis through
+    /// the origin
+    Rotate(f32, RotationAxis),
+    Translate(Vector),
+    Motion(Motion),
+}
+
+impl Transformation {
+    /// Intersects the transformed `primitive`
+    pub fn intersect(
+        &self,
+        primitive: &Primitive,
+        r: Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Intersection> {
+        match self {
+            Transformation::Rotate(angle, axis) => {
+                let rads = angle.to_radians();
+                let local = Ray {
+                    origin: rotate(r.origin, axis, -rads),
+                    dir: rotate(r.dir, axis, -rads),
+                    time: r.time,
+                };
+
+                primitive.intersect(local, t_min, t_max).map(|mut i| {
+                    i.p = rotate(i.p, axis, rads);
+                    i.normal = rotate(i.normal, axis, rads);
+                    i.tangent = i.tangent.map(|t| rotate(t, axis, rads));
+                    i
+                })
+            }
+            Transformation::Translate(offset) => {
+                translate(primitive, *offset, r, t_min, t_max)
+            }
+            Transformation::Motion(motion) => {
+                translate(primitive, motion.offset(r.time), r, t_min, t_max)
+            }
+        }
+    }
+
+    /// Bounds the transformed `bbox`. Moving objects are bounded over
+    /// their whole path.
+    pub fn bounding_box(&self, bbox: Aabb) -> Aabb {
+        match self {
+            Transformation::Rotate(angle, axis) => {
+                let rads = angle.to_radians();
+                let mut min = Vector::new(f32::MAX, f32::MAX, f32::MAX);
+                let mut max = Vector::new(f32::MIN, f32::MIN, f32::MIN);
+
+                for i in 0..8 {
+                    let corner = Vector::new(
+                        if i & 1 == 0 { bbox.min.x } else { bbox.max.x },
+                        if i & 2 == 0 { bbox.min.y } else { bbox.max.y },
+                        if i & 4 == 0 { bbox.min.z } else { bbox.max.z },
+                    );
+                    let corner = rotate(corner, axis, rads);
+
+                    for a in 0..3 {
+                        min[a] = f32::min(min[a], corner[a]);
+                        max[a] = f32::max(max[a], corner[a]);
+                    }
+                }
+
+                Aabb::new(min, max)
+            }
+            Transformation::Translate(offset) => {
+                Aabb::new(bbox.min + *offset, bbox.max + *offset)
+            }
+            Transformation::Motion(motion) => {
+                let moved = |offset: Vector| {
+                    Aabb::new(bbox.min + offset, bbox.max + offset)
+                };
+
+                // Only the positions along the path count, the object never
+                // sits at its untransformed position unless the path does
+                let corners = motion.corners();
+                corners[1..].iter().fold(moved(corners[0]), |b, &offset| {
+                    Aabb::surrounding_box(b, moved(offset))
+                })
+            }
+        }
+    }
+}
+
+fn translate(
+    primitive: &Primitive,
+    offset: Vector,
+    r: Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<Intersection> {
+    let local = Ray {
+        origin: r.origin - offset,
+        ..r
+    };
+
+    primitive.intersect(local, t_min, t_max).map(|mut i| {
+        i.p += offset;
+        i
+    })
+}
+
+fn rotate(v: Vector, axis: &RotationAxis, rads: f32) -> Vector {
+    let (a, b) = axis.plane();
+    let (sin, cos) = rads.sin_cos();
+
+    let mut rotated = v;
+    rotated[a] = cos * v[a] - sin * v[b];
+    rotated[b] = sin * v[a] + cos * v[b];
+    rotated
+}
+
+fn lerp_offset(time: f32, from: (f32, Vector), to: (f32, Vector)) -> Vector {
+    let span = to.0 - from.0;
+    if span <= 0.0 {
+        return to.1;
+    }
+
+    let s = ((time - from.0) / span).clamp(0.0, 1.0);
+    from.1 + s * (to.1 - from.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motion_bounds_only_cover_the_path() {
+        let motion = Transformation::Motion(Motion::Linear {
+            from: Vector::new(5.0, 0.0, 0.0),
+            to: Vector::new(8.0, 2.0, 0.0),
+            time0: 0.0,
+            time1: 1.0,
+        });
+
+        let unit = Aabb::new(Vector::new(-1.0, -1.0, -1.0), Vector::ones());
+        let bbox = motion.bounding_box(unit);
+
+        assert_eq!([bbox.min.x, bbox.min.y, bbox.min.z], [4.0, -1.0, -1.0]);
+        assert_eq!([bbox.max.x, bbox.max.y, bbox.max.z], [9.0, 3.0, 1.0]);
+    }
+}
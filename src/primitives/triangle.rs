@@ -63,43 +63,60 @@ impl Triangle {
         t_min: f32,
         t_max: f32,
     ) -> Option<Intersection> {
-        let h = r.dir.cross(self.edge2);
-        let a = self.edge1.dot(h);
-
-        if a > -EPSILON && a < EPSILON {
-            return None;
-        }
-
-        let f = 1.0 / a;
-        let s = r.origin - self.v0;
-        let u = f * s.dot(h);
-
-        if u < 0.0 || u > 1.0 {
-            return None;
-        }
-
-        let q = s.cross(self.edge1);
-        let v = f * r.dir.dot(q);
-
-        if v < 0.0 || u + v > 1.0 {
-            return None;
-        }
-
-        let t = f * self.edge2.dot(q);
-        if t > EPSILON && t < 1.0 / EPSILON && t > t_min && t < t_max {
-            Some(Intersection {
+        moller_trumbore(self.v0, self.edge1, self.edge2, r, t_min, t_max).map(
+            |(t, u, v)| Intersection {
                 u,
                 v,
                 t,
                 p: r.origin + r.dir * t,
                 normal: self.normal,
-            })
-        } else {
-            None
-        }
+                tangent: None,
+            },
+        )
     }
 
     pub fn bounding_box(&self) -> Aabb {
         self.bbox
     }
 }
+
+/// Intersects a ray with the triangle spanned by `v0`, `v0 + edge1` and
+/// `v0 + edge2`. Returns the ray parameter along with the barycentric
+/// coordinates of the hit.
+pub(super) fn moller_trumbore(
+    v0: Vector,
+    edge1: Vector,
+    edge2: Vector,
+    r: Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let h = r.dir.cross(edge2);
+    let a = edge1.dot(h);
+
+    if a > -EPSILON && a < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / a;
+    let s = r.origin - v0;
+    let u = f * s.dot(h);
+
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = f * r.dir.dot(q);
+
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = f * edge2.dot(q);
+    if t > EPSILON && t < 1.0 / EPSILON && t > t_min && t < t_max {
+        Some((t, u, v))
+    } else {
+        None
+    }
+}
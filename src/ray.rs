@@ -19,6 +19,9 @@ use crate::Vector;
 pub struct Ray {
     pub origin: Vector,
     pub dir: Vector,
+    /// The moment during the camera shutter interval at which the ray was
+    /// cast, for motion blur
+    pub time: f32,
 }
 
 impl Ray {
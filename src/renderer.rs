@@ -19,6 +19,9 @@ use rayon::prelude::*;
 use crate::{
     config::{Config, Scene},
     image::ImageBuffer,
+    lights::LightSampler,
+    math::power_heuristic,
+    media::Medium,
     ray::Ray,
     Vector,
 };
@@ -29,9 +32,13 @@ use std::io::Write;
 
 const T_MIN: f32 = 0.0005;
 const MAX_RECURSIVE_DEPTH: usize = 50;
+/// Light can scatter many times inside dense media like wax or marble, so
+/// scattering events in media are limited separately from surface bounces
+const MAX_MEDIUM_SCATTERS: usize = 1000;
 
-pub fn render(image: &mut ImageBuffer, config: Config) {
+pub fn render(image: &mut ImageBuffer, config: &Config) {
     let (width, height) = config.resolution;
+    let lights = LightSampler::new(&config.scene);
     let mut done_rows = 0;
 
     image.buffer.iter_mut().for_each(|row| {
@@ -45,12 +52,13 @@ pub fn render(image: &mut ImageBuffer, config: Config) {
                 let u = (pixel.x as f32 + rng.gen::<f32>()) / width as f32;
                 let v = (pixel.y as f32 + rng.gen::<f32>()) / height as f32;
 
-                let r = config.scene.camera.get_ray(u, v);
-
-                curr_pixel += trace(r, &config.scene);
+                if let Some(r) = config.scene.camera.get_ray(u, v) {
+                    curr_pixel += trace(r, &config.scene, &lights);
+                }
             }
 
-            curr_pixel /= config.samples as f32;
+            curr_pixel *=
+                config.scene.camera.exposure() / config.samples as f32;
 
             pixel.r = curr_pixel.x;
             pixel.g = curr_pixel.y;
@@ -64,28 +72,243 @@ pub fn render(image: &mut ImageBuffer, config: Config) {
     progress_bar(height, height);
 }
 
-pub fn trace(r: Ray, scene: &Scene) -> Vector {
+pub fn trace(r: Ray, scene: &Scene, lights: &LightSampler) -> Vector {
     let mut curr_ray = r;
     let mut curr_att = Vector::ones();
-    let mut total_emitted = Vector::zeros();
+    let mut radiance = Vector::zeros();
+    // The volumes the ray is currently inside of, innermost last
+    let mut media: Vec<Medium> = Vec::new();
+    // The pdf of the current ray's direction, if the light it finds was
+    // also sampled directly at its origin
+    let mut direct_pdf: Option<f32> = None;
+    let mut direct_origin = r.origin;
+    let mut bounces = 0;
+    let mut scatters = 0;
+
+    while bounces < MAX_RECURSIVE_DEPTH && scatters < MAX_MEDIUM_SCATTERS {
+        let hit_result = scene.hit(curr_ray, T_MIN, f32::MAX);
+
+        if let Some(medium) = media.last().or(scene.fog.as_ref()) {
+            let t_max = hit_result
+                .as_ref()
+                .map_or(f32::MAX, |hit| hit.intersection.t);
+            let sample = medium.sample(curr_ray, t_max);
+
+            curr_att *= sample.attenuation;
+            if let Some(scattered) = sample.scattered {
+                let phase = |dir| {
+                    let pdf = medium.phase(curr_ray.dir, dir);
+                    Some((Vector::ones() * pdf, pdf))
+                };
+
+                radiance += curr_att
+                    * direct_light(scene, lights, scattered, &media, phase);
+
+                direct_pdf = Some(medium.phase(curr_ray.dir, scattered.dir));
+                direct_origin = scattered.origin;
+                curr_ray = scattered;
+                scatters += 1;
+                continue;
+            }
+        }
+
+        let hit = match hit_result {
+            None => {
+                if let Some(background) = &scene.background {
+                    let weight = direct_pdf.map_or(1.0, |pdf| {
+                        power_heuristic(pdf, background.pdf(curr_ray.dir))
+                    });
+
+                    radiance +=
+                        curr_att * background.radiance(curr_ray.dir) * weight;
+                }
+
+                return radiance;
+            }
+            Some(hit) => hit,
+        };
+
+        // Emitters that were sampled directly are weighed against it
+        let weight = direct_pdf
+            .and_then(|pdf| {
+                lights
+                    .pdf(hit.object, direct_origin, hit.intersection)
+                    .map(|light_pdf| power_heuristic(pdf, light_pdf))
+            })
+            .unwrap_or(1.0);
+
+        radiance += curr_att * hit.emitted * weight;
+
+        let scatter = match hit.scattered {
+            None => return radiance,
+            Some(scatter) => scatter,
+        };
+
+        if let Some(medium) = hit.medium {
+            let normal = hit.intersection.normal;
+            let before = curr_ray.dir.dot(normal);
+            let after = scatter.specular.dir.dot(normal);
+
+            // Only rays that cross the surface enter or leave the medium
+            // behind it
+            if before < 0.0 && after < 0.0 {
+                media.push(medium);
+            } else if before > 0.0 && after > 0.0 {
+                media.pop();
+            }
+        }
+
+        // The edges of volumes leave rays as they are, so the light at
+        // the end of them has been sampled all the same
+        if !hit.material.is_volume_boundary(curr_ray.time) {
+            let (material, i) = (hit.material, hit.intersection);
+            let bsdf = |dir| material.bsdf(curr_ray, i, dir);
+
+            direct_pdf = bsdf(scatter.specular.dir).map(|(_, pdf)| pdf);
+            direct_origin = i.p;
+            if direct_pdf.is_some() {
+                let from = Ray {
+                    origin: i.p,
+                    ..curr_ray
+                };
+
+                radiance +=
+                    curr_att * direct_light(scene, lights, from, &media, bsdf);
+            }
+        }
+
+        curr_ray = scatter.specular;
+        curr_att *= scatter.attenuation;
+        bounces += 1;
+    }
+
+    // Loop broke - max recursive depth exceeded
+    radiance
+}
+
+/// Samples the background and one of the lights directly from the
+/// origin of `from`, and returns the light arriving from them. `bsdf`
+/// gives the scattering towards the viewer and its pdf for a direction.
+fn direct_light<F>(
+    scene: &Scene,
+    lights: &LightSampler,
+    from: Ray,
+    media: &[Medium],
+    bsdf: F,
+) -> Vector
+where
+    F: Fn(Vector) -> Option<(Vector, f32)>,
+{
+    let radiance = sample_background(scene, from, media, &bsdf);
+
+    let (sample, pmf) = match lights.sample(from) {
+        Some(value) => value,
+        None => return radiance,
+    };
+
+    if sample.radiance.x <= 0.0
+        && sample.radiance.y <= 0.0
+        && sample.radiance.z <= 0.0
+    {
+        return radiance;
+    }
+
+    let (f, scatter_pdf) = match bsdf(sample.dir) {
+        Some(value) if value.1 > 0.0 => value,
+        _ => return radiance,
+    };
+
+    let shadow = Ray {
+        dir: sample.dir,
+        ..from
+    };
+
+    // Stop short of the light so that it doesn't get in its own way
+    let light = f
+        * sample.radiance
+        * transmittance(scene, shadow, sample.distance - T_MIN, media)
+        / pmf;
+
+    radiance
+        + match sample.pdf {
+            Some(pdf) => light * power_heuristic(pmf * pdf, scatter_pdf) / pdf,
+            // Lights with no size can't be hit by scattered rays, so there
+            // is nothing to weigh them against
+            None => light,
+        }
+}
+
+/// Samples the background, weighing the sample against the same light
+/// being found by scattering
+fn sample_background<F>(
+    scene: &Scene,
+    from: Ray,
+    media: &[Medium],
+    bsdf: &F,
+) -> Vector
+where
+    F: Fn(Vector) -> Option<(Vector, f32)>,
+{
+    let sample = match scene.background.as_ref().and_then(|b| b.sample()) {
+        Some(sample) if sample.pdf > 0.0 => sample,
+        _ => return Vector::zeros(),
+    };
+
+    let (f, scatter_pdf) = match bsdf(sample.dir) {
+        Some(value) if value.1 > 0.0 => value,
+        _ => return Vector::zeros(),
+    };
+
+    let shadow = Ray {
+        dir: sample.dir,
+        ..from
+    };
+
+    f * sample.radiance
+        * transmittance(scene, shadow, f32::INFINITY, media)
+        * power_heuristic(sample.pdf, scatter_pdf)
+        / sample.pdf
+}
+
+/// The fraction of light that travels along the ray up to `t_end`
+/// without being blocked by a surface or absorbed by media on the way
+fn transmittance(
+    scene: &Scene,
+    r: Ray,
+    t_end: f32,
+    media: &[Medium],
+) -> Vector {
+    let mut media = media.to_vec();
+    let mut curr_ray = r;
+    let mut t_end = t_end;
+    let mut transmittance = Vector::ones();
 
     for _ in 0..MAX_RECURSIVE_DEPTH {
-        let hit_result = scene.objects.hit(curr_ray, T_MIN, f32::MAX);
+        let hit_result = scene.hit(curr_ray, T_MIN, t_end);
+
+        if let Some(medium) = media.last().or(scene.fog.as_ref()) {
+            let t_max =
+                hit_result.as_ref().map_or(t_end, |hit| hit.intersection.t);
+
+            transmittance *= medium.transmittance(curr_ray, t_max);
+        }
 
         match hit_result {
-            None => return Vector::zeros(),
-            Some(hit) => match hit.scattered {
-                None => return curr_att * (total_emitted + hit.emitted),
-                Some(scatter) => {
-                    curr_ray = scatter.specular;
-                    curr_att *= scatter.attenuation;
-                    total_emitted += hit.emitted;
+            None => return transmittance,
+            Some(hit) if hit.material.is_volume_boundary(curr_ray.time) => {
+                if curr_ray.dir.dot(hit.intersection.normal) < 0.0 {
+                    media.extend(hit.medium);
+                } else {
+                    media.pop();
                 }
-            },
+
+                curr_ray.origin = hit.intersection.p;
+                t_end -= hit.intersection.t;
+            }
+            Some(_) => return Vector::zeros(),
         }
     }
 
-    // Loop broke - max recursive depth exceeded
     Vector::zeros()
 }
 
@@ -14,33 +14,675 @@
  * along with Nit. If not, see <https://www.gnu.org/licenses/>.
  */
 use crate::{
-    primitives::{Primitive, Triangle},
+    aabb::Aabb,
+    primitives::{Mesh, MeshFace, Primitive, Triangle},
     Vector,
 };
-use std::io;
+
+use std::{collections::HashMap, error::Error, fmt, io};
+
+/// Size of the fixed header at the start of a binary STL file
+/// (80 byte comment followed by a 32 bit face count)
+const BINARY_HEADER_LEN: usize = 84;
+/// Size of a single face record in a binary STL file
+const BINARY_FACE_LEN: usize = 50;
+/// Number of bytes inspected when deciding whether a file is ASCII
+const ASCII_PROBE_LEN: usize = 512;
+
+#[derive(Debug)]
+pub enum StlLoadError {
+    /// The source could not be read
+    Io(io::Error),
+    /// A binary file ended before all of its declared faces were present
+    Truncated { expected: usize, found: usize },
+    /// A line in an ASCII file could not be parsed
+    Syntax { line: usize, message: String },
+    /// The file did not contain any usable faces
+    Empty,
+}
+
+impl fmt::Display for StlLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read STL file: {}", e),
+            Self::Truncated { expected, found } => write!(
+                f,
+                "Binary STL file is truncated: expected {} bytes, found {}",
+                expected, found
+            ),
+            Self::Syntax { line, message } => {
+                write!(
+                    f,
+                    "ASCII STL syntax error on line {}: {}",
+                    line, message
+                )
+            }
+            Self::Empty => write!(f, "STL file contains no usable faces"),
+        }
+    }
+}
+
+impl Error for StlLoadError {}
+
+impl From<io::Error> for StlLoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Controls which normals are assigned to the loaded triangles.
+///
+/// Like [`Mesh`], normals point out of the surface, on the side that the
+/// vertices wind counter-clockwise around (`edge1 × edge2`). A zeroed facet
+/// normal is replaced by the winding normal before the mode is applied.
+///
+/// [`Mesh`]: ../primitives/struct.Mesh.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StlNormals {
+    /// Use the facet normals stored in the file
+    Keep,
+    /// Negate the stored normals, for files that are modelled inside out
+    /// with both the winding and the facet normals pointing inwards
+    Flip,
+    /// Ignore the stored normals and compute them from the
+    /// vertex winding order
+    Recompute,
+}
+
+/// Converts a mesh between up axis conventions. Nit treats +Y as up,
+/// while most CAD tools and 3D printing models use +Z.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AxisConversion {
+    None,
+    ZUpToYUp,
+    YUpToZUp,
+}
+
+impl AxisConversion {
+    fn apply(self, v: Vector) -> Vector {
+        match self {
+            Self::None => v,
+            Self::ZUpToYUp => Vector::new(v.x, v.z, -v.y),
+            Self::YUpToZUp => Vector::new(v.x, -v.z, v.y),
+        }
+    }
+}
+
+/// The point of the mesh bounding box that gets moved to
+/// [`StlOptions::offset`]
+///
+/// [`StlOptions::offset`]: struct.StlOptions.html#structfield.offset
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    /// Leave the mesh where it was modelled
+    Original,
+    /// The center of the bounding box
+    Center,
+    /// The center of the bottom face of the bounding box, which is
+    /// handy for resting models on a floor
+    Base,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct StlOptions {
+    pub normals: StlNormals,
+    /// Applied before any of the placement options below
+    pub up_axis: AxisConversion,
+    pub anchor: Anchor,
+    /// If set, the mesh is uniformly scaled around its bounding box center
+    /// so that the longest side of the box has this length
+    pub fit_size: Option<f32>,
+    /// Translation applied after the mesh has been anchored and scaled
+    pub offset: Vector,
+}
+
+impl Default for StlOptions {
+    fn default() -> Self {
+        Self {
+            normals: StlNormals::Keep,
+            up_axis: AxisConversion::None,
+            anchor: Anchor::Original,
+            fit_size: None,
+            offset: Vector::zeros(),
+        }
+    }
+}
+
+/// Summary of a loaded mesh
+#[derive(Debug, Copy, Clone)]
+pub struct MeshStats {
+    /// Number of faces that were loaded
+    pub faces: usize,
+    /// Number of zero-area faces that were skipped
+    pub degenerate: usize,
+    /// Bounding box of the loaded faces
+    pub bounds: Aabb,
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min, max) = (self.bounds.min, self.bounds.max);
+        write!(
+            f,
+            "{} faces ({} degenerate skipped), bounds ({}, {}, {}) to ({}, {}, {})",
+            self.faces,
+            self.degenerate,
+            min.x,
+            min.y,
+            min.z,
+            max.x,
+            max.y,
+            max.z
+        )
+    }
+}
+
+pub struct StlMesh {
+    vertices: Vec<Vector>,
+    /// Vertex indices and normal of each usable face
+    faces: Vec<([usize; 3], Vector)>,
+    pub stats: MeshStats,
+}
+
+impl StlMesh {
+    /// Converts the faces into individual triangle primitives
+    pub fn triangles(self) -> Vec<Primitive> {
+        let vertices = self.vertices;
+        self.faces
+            .into_iter()
+            .map(|(f, normal)| {
+                Primitive::Triangle(Triangle::new(
+                    vertices[f[0]],
+                    vertices[f[1]],
+                    vertices[f[2]],
+                    normal,
+                ))
+            })
+            .collect()
+    }
+
+    /// Converts the faces into a single [`Mesh`] primitive sharing the
+    /// vertex buffer, which uses far less memory for large models
+    ///
+    /// [`Mesh`]: ../primitives/struct.Mesh.html
+    pub fn mesh(self) -> Mesh {
+        let mut normals = Vec::with_capacity(self.faces.len());
+        let faces = self
+            .faces
+            .into_iter()
+            .map(|(f, normal)| {
+                let n = normals.len() as u32;
+                normals.push(normal);
+                MeshFace {
+                    vertices: [f[0] as u32, f[1] as u32, f[2] as u32],
+                    normals: Some([n, n, n]),
+                    uvs: None,
+                }
+            })
+            .collect();
+
+        Mesh::new(self.vertices, normals, Vec::new(), faces)
+    }
+}
+
+/// Mesh data as it is stored in the file, before any
+/// filtering is applied
+struct RawMesh {
+    vertices: Vec<Vector>,
+    faces: Vec<([usize; 3], Vector)>,
+}
+
+impl RawMesh {
+    /// Applies the axis conversion and placement options to the mesh
+    fn place(&mut self, options: StlOptions) {
+        for v in self.vertices.iter_mut() {
+            *v = options.up_axis.apply(*v);
+        }
+
+        for (_, n) in self.faces.iter_mut() {
+            *n = options.up_axis.apply(*n);
+        }
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let mut min = Vector::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector::new(-f32::MAX, -f32::MAX, -f32::MAX);
+        for v in &self.vertices {
+            for a in 0..3 {
+                min[a] = f32::min(min[a], v[a]);
+                max[a] = f32::max(max[a], v[a]);
+            }
+        }
+
+        let center = (min + max) / 2.0;
+        let extent = max - min;
+        let longest = f32::max(extent.x, f32::max(extent.y, extent.z));
+
+        let scale = match options.fit_size {
+            Some(size) if longest > 0.0 => size / longest,
+            _ => 1.0,
+        };
+
+        let anchor = match options.anchor {
+            Anchor::Original => center,
+            Anchor::Center => Vector::zeros(),
+            Anchor::Base => Vector::new(0.0, extent.y * scale / 2.0, 0.0),
+        };
+
+        for v in self.vertices.iter_mut() {
+            *v = (*v - center) * scale + anchor + options.offset;
+        }
+    }
+}
 
 pub struct StlLoader;
 
 impl StlLoader {
-    pub fn parse<R>(source: &mut R) -> Vec<Primitive>
+    /// Parses an ASCII or binary STL file. Degenerate faces are skipped
+    /// and counted in the returned [`MeshStats`].
+    ///
+    /// [`MeshStats`]: struct.MeshStats.html
+    pub fn parse<R>(
+        source: &mut R,
+        options: StlOptions,
+    ) -> Result<StlMesh, StlLoadError>
     where
-        R: io::Read + io::Seek,
+        R: io::Read,
     {
-        let stl = stl_io::read_stl(source).unwrap();
-        let mut ret: Vec<Primitive> = Vec::new();
-
-        stl.faces.iter().for_each(|f| {
-            let v0 = stl.vertices[f.vertices[0]];
-            let v0 = Vector::new(v0[0], v0[1], v0[2]);
-            let v1 = stl.vertices[f.vertices[1]];
-            let v1 = Vector::new(v1[0], v1[1], v1[2]);
-            let v2 = stl.vertices[f.vertices[2]];
-            let v2 = Vector::new(v2[0], v2[1], v2[2]);
-
-            let normal = -Vector::new(f.normal[0], f.normal[1], f.normal[2]);
-            ret.push(Primitive::Triangle(Triangle::new(v0, v1, v2, normal)));
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+
+        let mut raw = if is_ascii(&bytes) {
+            parse_ascii(&String::from_utf8_lossy(&bytes))?
+        } else {
+            parse_binary(bytes)?
+        };
+
+        raw.place(options);
+
+        let mut faces = Vec::with_capacity(raw.faces.len());
+        let mut degenerate = 0;
+        let mut min = Vector::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector::new(-f32::MAX, -f32::MAX, -f32::MAX);
+
+        for (indices, file_normal) in raw.faces {
+            let v0 = raw.vertices[indices[0]];
+            let v1 = raw.vertices[indices[1]];
+            let v2 = raw.vertices[indices[2]];
+
+            let edge1 = v1 - v0;
+            let edge2 = v2 - v0;
+            let cross = edge1.cross(edge2);
+
+            if cross.length() <= f32::EPSILON * edge1.length() * edge2.length()
+            {
+                degenerate += 1;
+                continue;
+            }
+
+            // Plenty of exporters write zeroed facet normals, so fall
+            // back to the winding order when the stored one is unusable
+            let stored = if file_normal.length_squared() == 0.0 {
+                cross
+            } else {
+                file_normal
+            };
+
+            let normal = match options.normals {
+                StlNormals::Keep => stored.normalize(),
+                StlNormals::Flip => -stored.normalize(),
+                StlNormals::Recompute => cross.normalize(),
+            };
+
+            for v in &[v0, v1, v2] {
+                for a in 0..3 {
+                    min[a] = f32::min(min[a], v[a]);
+                    max[a] = f32::max(max[a], v[a]);
+                }
+            }
+
+            faces.push((indices, normal));
+        }
+
+        if faces.is_empty() {
+            return Err(StlLoadError::Empty);
+        }
+
+        Ok(StlMesh {
+            stats: MeshStats {
+                faces: faces.len(),
+                degenerate,
+                bounds: Aabb::new(min, max),
+            },
+            vertices: raw.vertices,
+            faces,
+        })
+    }
+}
+
+/// Binary files may also begin with `solid`, so the length is checked
+/// against the face count from the binary header as well. Failing that,
+/// the start of the file has to look like text.
+fn is_ascii(bytes: &[u8]) -> bool {
+    if !bytes.starts_with(b"solid") {
+        return false;
+    }
+
+    if bytes.len() >= BINARY_HEADER_LEN && binary_len(bytes) == bytes.len() {
+        return false;
+    }
+
+    // A truncated binary file won't match its face count, but the count
+    // and the vertex data after the header are never plain text
+    bytes
+        .iter()
+        .take(ASCII_PROBE_LEN)
+        .all(|&b| b.is_ascii() && b != 0)
+}
+
+/// The expected length of a binary STL file according to its header
+fn binary_len(bytes: &[u8]) -> usize {
+    let count =
+        u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]);
+    BINARY_HEADER_LEN + count as usize * BINARY_FACE_LEN
+}
+
+fn parse_binary(bytes: Vec<u8>) -> Result<RawMesh, StlLoadError> {
+    if bytes.len() < BINARY_HEADER_LEN {
+        return Err(StlLoadError::Truncated {
+            expected: BINARY_HEADER_LEN,
+            found: bytes.len(),
+        });
+    }
+
+    let expected = binary_len(&bytes);
+    if bytes.len() < expected {
+        return Err(StlLoadError::Truncated {
+            expected,
+            found: bytes.len(),
+        });
+    }
+
+    let stl = stl_io::read_stl(&mut io::Cursor::new(bytes))?;
+
+    Ok(RawMesh {
+        vertices: stl
+            .vertices
+            .iter()
+            .map(|v| Vector::new(v[0], v[1], v[2]))
+            .collect(),
+        faces: stl
+            .faces
+            .iter()
+            .map(|f| {
+                (
+                    f.vertices,
+                    Vector::new(f.normal[0], f.normal[1], f.normal[2]),
+                )
+            })
+            .collect(),
+    })
+}
+
+fn parse_ascii(text: &str) -> Result<RawMesh, StlLoadError> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+
+    let mut last_line = 0;
+    let mut next_line = |expected: &str| match lines.next() {
+        Some((n, l)) => {
+            last_line = n;
+            Ok((n, l))
+        }
+        None => Err(StlLoadError::Syntax {
+            line: last_line,
+            message: format!("unexpected end of file, expected `{}`", expected),
+        }),
+    };
+
+    let (n, l) = next_line("solid")?;
+    if !l.starts_with("solid") {
+        return Err(syntax_error(n, "expected `solid`", l));
+    }
+
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+    let mut lookup: HashMap<[u32; 3], usize> = HashMap::new();
+
+    loop {
+        let (n, l) = next_line("facet")?;
+        if l.starts_with("endsolid") {
+            break;
+        }
+
+        let normal = match l.strip_prefix("facet normal") {
+            Some(rest) => parse_triple(n, rest)?,
+            None => {
+                return Err(syntax_error(
+                    n,
+                    "expected `facet` or `endsolid`",
+                    l,
+                ))
+            }
+        };
+
+        let (n, l) = next_line("outer loop")?;
+        if l != "outer loop" {
+            return Err(syntax_error(n, "expected `outer loop`", l));
+        }
+
+        let mut indices = [0; 3];
+        for index in indices.iter_mut() {
+            let (n, l) = next_line("vertex")?;
+            let v = match l.strip_prefix("vertex") {
+                Some(rest) => parse_triple(n, rest)?,
+                None => return Err(syntax_error(n, "expected `vertex`", l)),
+            };
+
+            // Shared corners are written out once per face in ASCII files,
+            // so merge them back together to match the binary loader
+            let key = [v.x.to_bits(), v.y.to_bits(), v.z.to_bits()];
+            *index = *lookup.entry(key).or_insert_with(|| {
+                vertices.push(v);
+                vertices.len() - 1
+            });
+        }
+
+        for expected in &["endloop", "endfacet"] {
+            let (n, l) = next_line(expected)?;
+            if l != *expected {
+                return Err(syntax_error(
+                    n,
+                    &format!("expected `{}`", expected),
+                    l,
+                ));
+            }
+        }
+
+        faces.push((indices, normal));
+    }
+
+    Ok(RawMesh { vertices, faces })
+}
+
+fn parse_triple(line: usize, s: &str) -> Result<Vector, StlLoadError> {
+    let coords = s
+        .split_whitespace()
+        .map(|w| w.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| StlLoadError::Syntax {
+            line,
+            message: format!("invalid number: {}", e),
+        })?;
+
+    if coords.len() != 3 {
+        return Err(StlLoadError::Syntax {
+            line,
+            message: format!("expected 3 coordinates, found {}", coords.len()),
         });
+    }
+
+    Ok(Vector::new(coords[0], coords[1], coords[2]))
+}
+
+fn syntax_error(line: usize, message: &str, found: &str) -> StlLoadError {
+    StlLoadError::Syntax {
+        line,
+        message: format!("{}, found `{}`", message, found),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Facet = ([f32; 3], [[f32; 3]; 3]);
+
+    /// A counter-clockwise triangle in the XY plane, so its winding
+    /// normal is +Z
+    const CCW: [[f32; 3]; 3] =
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    /// Three points on a line
+    const COLLINEAR: [[f32; 3]; 3] =
+        [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]];
+
+    fn binary(facets: &[Facet], declared: u32) -> Vec<u8> {
+        let mut bytes = vec![0; 80];
+        bytes.extend_from_slice(&declared.to_le_bytes());
+
+        for (normal, vertices) in facets {
+            for v in std::iter::once(normal).chain(vertices) {
+                for c in v {
+                    bytes.extend_from_slice(&c.to_le_bytes());
+                }
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+
+        bytes
+    }
+
+    fn ascii(facets: &[Facet]) -> String {
+        let mut lines = vec![String::from("solid test")];
+
+        for (n, vertices) in facets {
+            lines.push(format!("facet normal {} {} {}", n[0], n[1], n[2]));
+            lines.push(String::from("outer loop"));
+            for v in vertices {
+                lines.push(format!("vertex {} {} {}", v[0], v[1], v[2]));
+            }
+            lines.push(String::from("endloop"));
+            lines.push(String::from("endfacet"));
+        }
+
+        lines.push(String::from("endsolid test"));
+        lines.join("\n")
+    }
+
+    fn parse(
+        bytes: &[u8],
+        normals: StlNormals,
+    ) -> Result<StlMesh, StlLoadError> {
+        let options = StlOptions {
+            normals,
+            ..StlOptions::default()
+        };
+        StlLoader::parse(&mut io::Cursor::new(bytes), options)
+    }
+
+    /// `normalize` uses an approximate inverse square root, so only the
+    /// direction is compared
+    fn assert_direction(a: Vector, b: Vector) {
+        assert!(a.dot(b) > 0.99, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let bytes = binary(&[([0.0, 0.0, 1.0], CCW)], 2);
+
+        match parse(&bytes, StlNormals::Keep) {
+            Err(StlLoadError::Truncated { expected, found }) => {
+                assert_eq!(expected, BINARY_HEADER_LEN + 2 * BINARY_FACE_LEN);
+                assert_eq!(found, BINARY_HEADER_LEN + BINARY_FACE_LEN);
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("loaded a truncated file"),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_ascii_keyword() {
+        let text =
+            ascii(&[([0.0, 0.0, 1.0], CCW)]).replace("outer loop", "outer lop");
+
+        match parse(text.as_bytes(), StlNormals::Keep) {
+            Err(StlLoadError::Syntax { line, message }) => {
+                assert_eq!(line, 3);
+                assert!(message.contains("outer lop"), "{}", message);
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("loaded a malformed file"),
+        }
+    }
+
+    #[test]
+    fn rejects_only_degenerate_faces() {
+        let bytes = binary(&[([0.0, 0.0, 1.0], COLLINEAR)], 1);
+
+        assert!(matches!(
+            parse(&bytes, StlNormals::Keep),
+            Err(StlLoadError::Empty)
+        ));
+    }
+
+    #[test]
+    fn counts_degenerate_faces() {
+        let facets = [([0.0, 0.0, 1.0], CCW), ([0.0, 0.0, 1.0], COLLINEAR)];
+
+        for bytes in &[binary(&facets, 2), ascii(&facets).into_bytes()] {
+            let mesh = parse(bytes, StlNormals::Keep).unwrap();
+            assert_eq!(mesh.stats.faces, 1);
+            assert_eq!(mesh.stats.degenerate, 1);
+            assert_eq!(mesh.faces.len(), 1);
+        }
+    }
+
+    #[test]
+    fn assigns_normals_for_each_mode() {
+        let up = Vector::new(0.0, 0.0, 1.0);
+
+        // The stored normal disagrees with the winding, so each mode
+        // gives a different answer
+        let text = ascii(&[([0.0, 0.0, -2.0], CCW)]);
+        let cases = [
+            (StlNormals::Keep, -up),
+            (StlNormals::Flip, up),
+            (StlNormals::Recompute, up),
+        ];
+        for &(mode, expected) in &cases {
+            let mesh = parse(text.as_bytes(), mode).unwrap();
+            assert_direction(mesh.faces[0].1, expected);
+        }
+    }
+
+    #[test]
+    fn zeroed_normals_follow_the_winding() {
+        let up = Vector::new(0.0, 0.0, 1.0);
 
-        ret
+        let text = ascii(&[([0.0, 0.0, 0.0], CCW)]);
+        let cases = [
+            (StlNormals::Keep, up),
+            (StlNormals::Flip, -up),
+            (StlNormals::Recompute, up),
+        ];
+        for &(mode, expected) in &cases {
+            let mesh = parse(text.as_bytes(), mode).unwrap();
+            assert_direction(mesh.faces[0].1, expected);
+        }
     }
 }
@@ -150,6 +150,19 @@ impl ops::Add<f32> for Vector {
     }
 }
 
+/// Subtract a float from all fields in vector
+impl ops::Sub<f32> for Vector {
+    type Output = Vector;
+
+    fn sub(self, rhs: f32) -> Vector {
+        Vector {
+            x: self.x - rhs,
+            y: self.y - rhs,
+            z: self.z - rhs,
+        }
+    }
+}
+
 /// Subtract two vectors
 impl ops::Sub<Vector> for Vector {
     type Output = Vector;
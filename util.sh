@@ -37,7 +37,7 @@ case $1 in
         ;;
     iter)
         make build \
-        && ./target/release/nit $2 out/image.ppm \
+        && ./target/release/nit \
         && feh --auto-zoom --force-aliasing out/image.ppm
         ;;
     profile)
@@ -66,15 +66,11 @@ case $1 in
         echo "$2 / 60; $2 % 60" | bc
         ;;
     gen)
-        rm -f scenes/animation/*
         rm -f out/*
-        node scripts/animation_1.js
+        fps=$(./target/release/nit --fps) || exit 1
+        ./target/release/nit --frames $2 $3 || exit 1
 
-        for f in $(ls scenes/animation/); do
-            ./target/release/nit "./scenes/animation/$f" "out/$f.ppm"
-        done
-
-        ffmpeg -pattern_type glob -framerate 25 -i "out/*.ppm" -c:v libx264 -crf 25 -b:v 40M -pix_fmt yuv420p "out/animation_1.mp4"
+        ffmpeg -pattern_type glob -framerate $fps -i "out/frame_*.ppm" -c:v libx264 -crf 25 -b:v 40M -pix_fmt yuv420p "out/animation_1.mp4"
         ;;
     *)
         echo "unknown command"